@@ -0,0 +1,117 @@
+use std::collections::HashSet;
+
+use crate::ast::{Block, Expr, ExprKind, Field, Function, LocalId, StmtKind, Variable};
+
+/**
+ * Locals used by a function declared in their scope. They have to outlive the call that declared
+ * them, so they are kept in cells shared with the closures instead of plain C variables.
+ */
+pub fn captured_locals(block: &Block) -> HashSet<LocalId> {
+    let mut locals = Locals::default();
+    locals.block(block);
+
+    let mut captured = HashSet::new();
+    for function in locals.functions {
+        captured.extend(free_locals(function).into_iter().map(|(_, id)| id));
+    }
+
+    return captured;
+}
+
+/**
+ * Locals of enclosing functions that a function uses, in the order they are first used. A local
+ * only used by a function nested in it counts too, as it has to be passed on.
+ */
+pub fn free_locals(function: &Function) -> Vec<(String, LocalId)> {
+    let mut locals = Locals::default();
+    locals.function(function);
+
+    return locals.used.into_iter()
+        .filter(|(_, id)| ! locals.declared.contains(id))
+        .map(|(name, id)| (name.to_string(), id))
+        .collect();
+}
+
+/**
+ * Locals declared and used in a part of the program, nested functions included.
+ */
+#[derive(Default)]
+struct Locals<'a> {
+    declared: HashSet<LocalId>,
+    used: Vec<(&'a str, LocalId)>,
+    functions: Vec<&'a Function>,
+}
+
+impl<'a> Locals<'a> {
+    fn block(&mut self, block: &'a Block) -> () {
+        for stmt in &block.stmts {
+            match &stmt.kind {
+                StmtKind::Print(value) | StmtKind::Call(value) => self.expr(value),
+                StmtKind::Assign(targets, values) => {
+                    targets.iter().chain(values).for_each(|expr| self.expr(expr));
+                },
+                StmtKind::Local(names, values) => {
+                    values.iter().for_each(|value| self.expr(value));
+                    self.declared.extend(names.iter().map(|(_, id)| *id));
+                },
+                StmtKind::LocalFunction(_, id, function) => {
+                    self.declared.insert(*id);
+                    self.function(function);
+                },
+                StmtKind::Return(values) => values.iter().for_each(|value| self.expr(value)),
+                StmtKind::If(condition, body, else_body) => {
+                    self.expr(condition);
+                    self.block(body);
+                    if let Some(else_body) = else_body {
+                        self.block(else_body);
+                    }
+                },
+                StmtKind::While(condition, body) => {
+                    self.expr(condition);
+                    self.block(body);
+                },
+                StmtKind::Do(body) => self.block(body),
+                StmtKind::Input(_) | StmtKind::Label(_) | StmtKind::Goto(_) => (),
+            }
+        }
+    }
+
+    fn function(&mut self, function: &'a Function) -> () {
+        self.functions.push(function);
+        self.declared.extend(function.params.iter().map(|(_, id)| *id));
+        self.block(&function.body);
+    }
+
+    fn expr(&mut self, expr: &'a Expr) -> () {
+        match &expr.kind {
+            ExprKind::Variable(Variable::Local(name, id)) => {
+                if ! self.used.iter().any(|(_, used)| used == id) {
+                    self.used.push((name, *id));
+                }
+            },
+            ExprKind::Unary(_, operand) | ExprKind::Paren(operand) => self.expr(operand),
+            ExprKind::Binary(_, left, right) | ExprKind::Index(left, right) => {
+                self.expr(left);
+                self.expr(right);
+            },
+            ExprKind::Table(fields) => {
+                for field in fields {
+                    match field {
+                        Field::Positional(value) => self.expr(value),
+                        Field::Keyed(key, value) => {
+                            self.expr(key);
+                            self.expr(value);
+                        },
+                    }
+                }
+            },
+            ExprKind::Function(function) => self.function(function),
+            ExprKind::Call(function, args) => {
+                self.expr(function);
+                args.iter().for_each(|arg| self.expr(arg));
+            },
+            ExprKind::Nil | ExprKind::Boolean(_) | ExprKind::Integer(_) | ExprKind::Float(_)
+            | ExprKind::String(_) | ExprKind::Variable(Variable::Global(_)) => (),
+        }
+    }
+}
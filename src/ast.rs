@@ -0,0 +1,405 @@
+use std::fmt;
+
+use crate::span::Span;
+
+/**
+ * Sequence of statements, e.g. the whole program or the body of a loop.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+}
+
+impl Block {
+    pub fn new(stmts: Vec<Stmt>, span: Span) -> Self {
+        return Block { stmts, span };
+    }
+}
+
+/**
+ * Identifier together with where it was written.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Name {
+    pub name: String,
+    pub span: Span,
+}
+
+impl Name {
+    pub fn new(name: String, span: Span) -> Self {
+        return Name { name, span };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        return Stmt { kind, span };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StmtKind {
+    // Print a value followed by a new line.
+    Print(Expr),
+    // Read a number from stdin into a variable.
+    Input(Name),
+    // Targets, which are variables or indexes, and their values, all computed before any is stored.
+    // Missing values are nil, extra ones are dropped, as for Local.
+    Assign(Vec<Expr>, Vec<Expr>),
+    // New locals, in scope from the next statement on, and their values. Missing values are nil, extra ones are dropped.
+    Local(Vec<(Name, LocalId)>, Vec<Expr>),
+    // A block of its own, ending the scope of its locals.
+    Do(Block),
+    // local function f, where f is already in scope in the body so it can call itself.
+    LocalFunction(Name, LocalId, Function),
+    // A call whose results are dropped.
+    Call(Expr),
+    // Ends the function, the last value may be a call giving any number of them.
+    Return(Vec<Expr>),
+    // Condition, then and else branches; an "elseif" is an if alone in the else branch.
+    If(Expr, Block, Option<Block>),
+    While(Expr, Block),
+    Label(Name),
+    Goto(Name),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        return Expr { kind, span };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExprKind {
+    Nil,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    // Bytes of a string constant, see lexer::Literal::String.
+    String(Vec<u8>),
+    Variable(Variable),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Function(Box<Function>),
+    // Function and arguments.
+    Call(Box<Expr>, Vec<Expr>),
+    // Parenthesised expression: not a variable that can be assigned, and a call gives only its first result.
+    Paren(Box<Expr>),
+    Table(Vec<Field>),
+    // Table and key; t.name is t["name"].
+    Index(Box<Expr>, Box<Expr>),
+}
+
+/**
+ * Entry of a table constructor.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Field {
+    // Value stored at the next integer key, counting from 1.
+    Positional(Expr),
+    // [key] = value, or name = value with the name as a string key.
+    Keyed(Expr, Expr),
+}
+
+impl ExprKind {
+    /**
+     * Whether the expression can give any number of values, which it does at the end of a list.
+     */
+    pub fn is_multi_valued(&self) -> bool {
+        return matches!(self, ExprKind::Call(..));
+    }
+}
+
+/**
+ * Parameters and body of a function, and the name it was defined with if any.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Function {
+    pub name: Option<String>,
+    pub params: Vec<(Name, LocalId)>,
+    pub body: Block,
+}
+
+/**
+ * Number telling a local apart from every other local in the program, even one with the same name.
+ */
+pub type LocalId = usize;
+
+/**
+ * What a name refers to: the innermost local declared with it, or else a global.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Variable {
+    Global(String),
+    Local(String, LocalId),
+}
+
+impl fmt::Display for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Variable::Global(name) => write!(f, "{name}"),
+            Variable::Local(name, id) => write!(f, "{name} (local {id})"),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+    // Length, '#'.
+    Len,
+    // Bitwise not, a prefix '~'.
+    BNot,
+}
+
+/**
+ * Precedence of the unary operators, only '^' binds tighter: -x^2 is -(x^2).
+ */
+pub const UNARY_PRECEDENCE: u8 = 11;
+
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+pub enum BinaryOp {
+    Or,
+    And,
+    BOr,
+    BXor,
+    BAnd,
+    Shl,
+    Shr,
+    Concat,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    // Floor division, '//'.
+    IDiv,
+    Mod,
+    Pow,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+impl BinaryOp {
+    /**
+     * Binding strength, higher binds tighter. These are Lua 5.4's levels, with the unary operators at UNARY_PRECEDENCE.
+     */
+    pub fn precedence(&self) -> u8 {
+        return match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Eq | BinaryOp::NotEq
+            | BinaryOp::Lt | BinaryOp::LtEq | BinaryOp::Gt | BinaryOp::GtEq => 3,
+            BinaryOp::BOr => 4,
+            BinaryOp::BXor => 5,
+            BinaryOp::BAnd => 6,
+            BinaryOp::Shl | BinaryOp::Shr => 7,
+            BinaryOp::Concat => 8,
+            BinaryOp::Add | BinaryOp::Sub => 9,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::IDiv | BinaryOp::Mod => 10,
+            BinaryOp::Pow => 12,
+        };
+    }
+
+    /**
+     * a .. b .. c is a .. (b .. c) and a ^ b ^ c is a ^ (b ^ c), the rest group from the left.
+     */
+    pub fn is_right_associative(&self) -> bool {
+        return matches!(self, BinaryOp::Concat | BinaryOp::Pow);
+    }
+}
+
+impl fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnaryOp::Neg => write!(f, "-"),
+            UnaryOp::Not => write!(f, "not"),
+            UnaryOp::Len => write!(f, "#"),
+            UnaryOp::BNot => write!(f, "~"),
+        }
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            BinaryOp::Or => "or",
+            BinaryOp::And => "and",
+            BinaryOp::BOr => "|",
+            BinaryOp::BXor => "~",
+            BinaryOp::BAnd => "&",
+            BinaryOp::Shl => "<<",
+            BinaryOp::Shr => ">>",
+            BinaryOp::Concat => "..",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::IDiv => "//",
+            BinaryOp::Mod => "%",
+            BinaryOp::Pow => "^",
+            BinaryOp::Eq => "==",
+            BinaryOp::NotEq => "~=",
+            BinaryOp::Lt => "<",
+            BinaryOp::LtEq => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::GtEq => ">=",
+        };
+
+        write!(f, "{text}")
+    }
+}
+
+/**
+ * Indented tree view of a block, one node per line with its location.
+ */
+pub fn dump(block: &Block) -> String {
+    let mut out = String::new();
+    dump_block(&mut out, block, 0);
+    return out;
+}
+
+fn dump_block(out: &mut String, block: &Block, depth: usize) -> () {
+    line(out, depth, "Block", &block.span);
+    for stmt in &block.stmts {
+        dump_stmt(out, stmt, depth + 1);
+    }
+}
+
+fn dump_stmt(out: &mut String, stmt: &Stmt, depth: usize) -> () {
+    match &stmt.kind {
+        StmtKind::Print(value) => {
+            line(out, depth, "Print", &stmt.span);
+            dump_expr(out, value, depth + 1);
+        },
+        StmtKind::Input(name) => line(out, depth, &format!("Input {}", name.name), &stmt.span),
+        StmtKind::Assign(targets, values) => {
+            line(out, depth, "Assign", &stmt.span);
+            for expr in targets.iter().chain(values) {
+                dump_expr(out, expr, depth + 1);
+            }
+        },
+        StmtKind::Local(names, values) => {
+            let names: Vec<String> = names.iter().map(|(name, id)| format!("{} {id}", name.name)).collect();
+            line(out, depth, &format!("Local {}", names.join(", ")), &stmt.span);
+            for value in values {
+                dump_expr(out, value, depth + 1);
+            }
+        },
+        StmtKind::Do(body) => {
+            line(out, depth, "Do", &stmt.span);
+            dump_block(out, body, depth + 1);
+        },
+        StmtKind::LocalFunction(name, id, function) => {
+            line(out, depth, &format!("LocalFunction {} {id}", name.name), &stmt.span);
+            dump_function(out, function, &stmt.span, depth + 1);
+        },
+        StmtKind::Call(call) => {
+            line(out, depth, "Call", &stmt.span);
+            dump_expr(out, call, depth + 1);
+        },
+        StmtKind::Return(values) => {
+            line(out, depth, "Return", &stmt.span);
+            for value in values {
+                dump_expr(out, value, depth + 1);
+            }
+        },
+        StmtKind::If(condition, body, else_body) => {
+            line(out, depth, "If", &stmt.span);
+            dump_expr(out, condition, depth + 1);
+            dump_block(out, body, depth + 1);
+            if let Some(else_body) = else_body {
+                dump_block(out, else_body, depth + 1);
+            }
+        },
+        StmtKind::While(condition, body) => {
+            line(out, depth, "While", &stmt.span);
+            dump_expr(out, condition, depth + 1);
+            dump_block(out, body, depth + 1);
+        },
+        StmtKind::Label(name) => line(out, depth, &format!("Label {}", name.name), &stmt.span),
+        StmtKind::Goto(name) => line(out, depth, &format!("Goto {}", name.name), &stmt.span),
+    }
+}
+
+fn dump_expr(out: &mut String, expr: &Expr, depth: usize) -> () {
+    match &expr.kind {
+        ExprKind::Nil => line(out, depth, "Nil", &expr.span),
+        ExprKind::Boolean(value) => line(out, depth, &format!("Boolean {value}"), &expr.span),
+        ExprKind::Integer(value) => line(out, depth, &format!("Integer {value}"), &expr.span),
+        ExprKind::Float(value) => line(out, depth, &format!("Float {value:?}"), &expr.span),
+        ExprKind::String(bytes) => line(out, depth, &format!("String \"{}\"", bytes.escape_ascii()), &expr.span),
+        ExprKind::Variable(variable) => line(out, depth, &format!("Variable {variable}"), &expr.span),
+        ExprKind::Unary(op, operand) => {
+            line(out, depth, &format!("Unary {op}"), &expr.span);
+            dump_expr(out, operand, depth + 1);
+        },
+        ExprKind::Binary(op, left, right) => {
+            line(out, depth, &format!("Binary {op}"), &expr.span);
+            dump_expr(out, left, depth + 1);
+            dump_expr(out, right, depth + 1);
+        },
+        ExprKind::Function(function) => dump_function(out, function, &expr.span, depth),
+        ExprKind::Call(function, args) => {
+            line(out, depth, "Call", &expr.span);
+            dump_expr(out, function, depth + 1);
+            for arg in args {
+                dump_expr(out, arg, depth + 1);
+            }
+        },
+        ExprKind::Paren(inner) => {
+            line(out, depth, "Paren", &expr.span);
+            dump_expr(out, inner, depth + 1);
+        },
+        ExprKind::Table(fields) => {
+            line(out, depth, "Table", &expr.span);
+            for field in fields {
+                match field {
+                    Field::Positional(value) => dump_expr(out, value, depth + 1),
+                    Field::Keyed(key, value) => {
+                        line(out, depth + 1, "Field", &key.span.to(&value.span));
+                        dump_expr(out, key, depth + 2);
+                        dump_expr(out, value, depth + 2);
+                    },
+                }
+            }
+        },
+        ExprKind::Index(table, key) => {
+            line(out, depth, "Index", &expr.span);
+            dump_expr(out, table, depth + 1);
+            dump_expr(out, key, depth + 1);
+        },
+    }
+}
+
+fn dump_function(out: &mut String, function: &Function, span: &Span, depth: usize) -> () {
+    let params: Vec<String> = function.params.iter().map(|(name, id)| format!("{} {id}", name.name)).collect();
+    let name = function.name.as_deref().unwrap_or("(anonymous)");
+    line(out, depth, &format!("Function {name} ({})", params.join(", ")), span);
+    dump_block(out, &function.body, depth + 1);
+}
+
+fn line(out: &mut String, depth: usize, text: &str, span: &Span) -> () {
+    *out += &format!("{}{text} @ {}:{}\n", "  ".repeat(depth), span.line, span.column);
+}
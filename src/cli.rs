@@ -0,0 +1,211 @@
+use lua_compiler::diagnostic::ErrorFormat;
+use lua_compiler::lexer::Dialect;
+use lua_compiler::parser::DEFAULT_MAX_ERRORS;
+use lua_compiler::trace::Tracer;
+use lua_compiler::Emit;
+
+pub const USAGE: &str = "\
+Usage: lua-compiler [OPTIONS] <INPUT>...
+
+Compiles each INPUT file to C. Use - to read the program from stdin.
+
+Options:
+  -o, --output <PATH>        Write the output to PATH, or - for stdout (only with a single input)
+      --create-dirs          Create the output's missing parent directories
+      --dialect <LANG>       Read the input as basic or lua [default: lua for .lua files, else basic]
+      --emit <STAGE>         Stop after STAGE: tokens, ast or c [default: c]
+      --error-format <FMT>   Report errors as human, json or sarif [default: human]
+      --max-errors <N>       Stop after reporting N errors [default: 20]
+      --trace-lexer          Trace every token read (also LUA_COMPILER_TRACE=lexer)
+      --trace-parser         Trace every grammar rule parsed (also LUA_COMPILER_TRACE=parser)
+  -q, --quiet                Only print errors
+  -v, --verbose              Print what is being compiled and where it goes
+  -h, --help                 Print this help
+  -V, --version              Print the version
+
+Exit codes:
+  0  success
+  1  the program has errors
+  2  invalid command line
+  3  a file could not be read or written";
+
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+/**
+ * Everything given on the command line.
+ */
+#[derive(Debug)]
+pub struct Options {
+    pub inputs: Vec<String>,
+    pub output: Option<String>,
+    pub create_dirs: bool,
+    // None picks the dialect from each input's extension.
+    pub dialect: Option<Dialect>,
+    pub emit: Emit,
+    pub error_format: ErrorFormat,
+    pub max_errors: usize,
+    pub verbosity: Verbosity,
+    pub trace_lexer: bool,
+    pub trace_parser: bool,
+    pub help: bool,
+    pub version: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        return Options {
+            inputs: Vec::new(),
+            output: None,
+            create_dirs: false,
+            dialect: None,
+            emit: Emit::C,
+            error_format: ErrorFormat::Human,
+            max_errors: DEFAULT_MAX_ERRORS,
+            verbosity: Verbosity::Normal,
+            trace_lexer: Tracer::enabled_by_env("lexer"),
+            trace_parser: Tracer::enabled_by_env("parser"),
+            help: false,
+            version: false,
+        };
+    }
+}
+
+/**
+ * Parse the command line arguments, without the program name.
+ */
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        // Split "--name=value" so it can be handled the same as "--name value".
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if arg.starts_with("--") => (name.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+
+        let mut value = |name: &str| -> Result<String, String> {
+            return inline_value.clone()
+                .or_else(|| args.next())
+                .ok_or(format!("Missing value for {name}"));
+        };
+
+        match name.as_str() {
+            "-o" | "--output" => options.output = Some(value(&name)?),
+            "--create-dirs" => options.create_dirs = true,
+            "--dialect" => options.dialect = Some(Dialect::try_from(value(&name)?.as_str())?),
+            "--emit" => options.emit = Emit::try_from(value(&name)?.as_str())?,
+            "--error-format" => options.error_format = ErrorFormat::try_from(value(&name)?.as_str())?,
+            "--max-errors" => {
+                let max_errors = value(&name)?;
+                options.max_errors = max_errors.parse()
+                    .map_err(|_| format!("Invalid number for --max-errors: {max_errors}"))?;
+            },
+            "--trace-lexer" => options.trace_lexer = true,
+            "--trace-parser" => options.trace_parser = true,
+            "-q" | "--quiet" => options.verbosity = Verbosity::Quiet,
+            "-v" | "--verbose" => options.verbosity = Verbosity::Verbose,
+            "-h" | "--help" => options.help = true,
+            "-V" | "--version" => options.version = true,
+            "-" => options.inputs.push(arg),
+            _ if arg.starts_with('-') => return Err(format!("Unknown option: {arg}")),
+            _ => options.inputs.push(arg),
+        }
+    }
+
+    if options.help || options.version {
+        return Ok(options);
+    }
+
+    if options.inputs.is_empty() {
+        return Err("No input files".to_string());
+    }
+
+    if options.output.is_some() && options.inputs.len() > 1 {
+        return Err("-o can only be used with a single input file".to_string());
+    }
+
+    return Ok(options);
+}
+
+/**
+ * Where the output for an input goes when -o isn't given: next to the input, with the stage's extension.
+ */
+pub fn default_output(input: &str, emit: Emit) -> String {
+    let extension = emit.extension();
+
+    if input == "-" {
+        return format!("out.{extension}");
+    }
+
+    return std::path::Path::new(input).with_extension(extension).to_string_lossy().to_string();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_output, parse_args, Options, Verbosity};
+    use lua_compiler::diagnostic::ErrorFormat;
+    use lua_compiler::lexer::Dialect;
+    use lua_compiler::Emit;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        return parse_args(args.iter().map(|arg| arg.to_string()));
+    }
+
+    #[test]
+    fn options_take_values_inline_or_as_the_next_argument() {
+        let options = parse(&["--dialect=lua", "--emit", "ast", "-o", "out.ast", "--error-format=sarif", "--max-errors", "5", "-q", "main.lua"]).unwrap();
+
+        assert_eq!(options.inputs, vec!["main.lua"]);
+        assert_eq!(options.output.as_deref(), Some("out.ast"));
+        assert_eq!(options.dialect, Some(Dialect::Lua));
+        assert_eq!(options.emit, Emit::Ast);
+        assert_eq!(options.error_format, ErrorFormat::Sarif);
+        assert_eq!(options.max_errors, 5);
+        assert_eq!(options.verbosity, Verbosity::Quiet);
+    }
+
+    #[test]
+    fn defaults() {
+        let options = parse(&["a.lua", "-", "b.bas"]).unwrap();
+
+        assert_eq!(options.inputs, vec!["a.lua", "-", "b.bas"]);
+        assert_eq!(options.output, None);
+        assert_eq!(options.dialect, None);
+        assert_eq!(options.emit, Emit::C);
+        assert_eq!(options.error_format, ErrorFormat::Human);
+        assert_eq!(options.verbosity, Verbosity::Normal);
+        assert!(! options.create_dirs);
+    }
+
+    #[test]
+    fn help_and_version_need_no_input() {
+        assert!(parse(&["--help"]).unwrap().help);
+        assert!(parse(&["-V"]).unwrap().version);
+    }
+
+    #[test]
+    fn invalid_command_lines() {
+        assert_eq!(parse(&[]).unwrap_err(), "No input files");
+        assert_eq!(parse(&["--bogus", "a.lua"]).unwrap_err(), "Unknown option: --bogus");
+        assert_eq!(parse(&["a.lua", "--emit"]).unwrap_err(), "Missing value for --emit");
+        assert_eq!(parse(&["--max-errors=x", "a.lua"]).unwrap_err(), "Invalid number for --max-errors: x");
+        assert_eq!(parse(&["-o", "out.c", "a.lua", "b.lua"]).unwrap_err(), "-o can only be used with a single input file");
+        assert!(parse(&["--dialect", "cobol", "a.lua"]).is_err());
+        assert!(parse(&["--error-format", "xml", "a.lua"]).is_err());
+    }
+
+    #[test]
+    fn output_defaults_to_the_input_with_the_stage_extension() {
+        assert_eq!(default_output("src/main.lua", Emit::C), "src/main.c");
+        assert_eq!(default_output("prog.bas", Emit::Tokens), "prog.tokens");
+        assert_eq!(default_output("-", Emit::Ast), "out.ast");
+    }
+}
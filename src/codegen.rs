@@ -0,0 +1,833 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::analysis::{captured_locals, free_locals};
+use crate::ast::{BinaryOp, Block, Expr, ExprKind, Field, Function, LocalId, Stmt, StmtKind, UnaryOp, Variable};
+use crate::emitter::Emitter;
+
+/**
+ * C definitions of Lua's values and operators, copied to the top of every generated program.
+ */
+const RUNTIME: &str = include_str!("runtime.c");
+
+/**
+ * Globals holding a value of the runtime from the start: Lua name and C initializer.
+ */
+const BUILTINS: [(&str, &str); 2] = [
+    ("print", "{ .type = LUA_FUNCTION, .as.function = &lua_print_closure }"),
+    ("table", "{ .type = LUA_TABLE, .as.table = &lua_table_library }"),
+];
+
+/**
+ * Walks the AST and writes the equivalent C program to the emitter.
+ */
+pub struct Generator<'a> {
+    emitter: &'a mut Emitter,
+    declared: HashSet<String>,
+    // C code of the functions being generated, innermost last; they go to the header once complete.
+    functions: Vec<String>,
+    // Locals of enclosing functions each of those functions uses, in the order of its closure's cells.
+    upvalues: Vec<Vec<(String, LocalId)>>,
+    // Locals kept in cells as closures use them.
+    captured: HashSet<LocalId>,
+    // C names of the labels of each enclosing block of the function, innermost last.
+    labels: Vec<HashMap<String, String>>,
+    // C labels the function already has; they reach the whole function.
+    label_names: HashSet<String>,
+    temp_count: usize,
+    function_count: usize,
+}
+
+impl<'a> Generator<'a> {
+    pub fn new(emitter: &'a mut Emitter) -> Self {
+        return Generator {
+            emitter,
+            declared: HashSet::new(),
+            functions: Vec::new(),
+            upvalues: Vec::new(),
+            captured: HashSet::new(),
+            labels: Vec::new(),
+            label_names: HashSet::new(),
+            temp_count: 0,
+            function_count: 0,
+        };
+    }
+
+    pub fn program(&mut self, block: &Block) -> () {
+        self.emitter.header_line(RUNTIME.trim_end());
+        self.emitter.header_line("");
+        self.emit_line("int main (void) {");
+        self.emit_line("lua_open();");
+
+        self.captured = captured_locals(block);
+        self.block(block);
+
+        self.emit_line("return 0;");
+        self.emit_line("}");
+    }
+
+    /**
+     * Write a line to the function being generated, or to main.
+     */
+    fn emit_line(&mut self, code: &str) -> () {
+        match self.functions.last_mut() {
+            Some(function) => *function += &format!("{code}\n"),
+            None => self.emitter.emit_line(code),
+        }
+    }
+
+    /**
+     * New C variable name, never used before.
+     */
+    fn temp(&mut self, prefix: &str) -> String {
+        self.temp_count += 1;
+        return format!("{prefix}{}", self.temp_count);
+    }
+
+    fn block(&mut self, block: &Block) -> () {
+        // Named before the statements, a goto can jump to a label further on.
+        let mut labels = HashMap::new();
+        for stmt in &block.stmts {
+            if let StmtKind::Label(name) = &stmt.kind {
+                let c_name = self.label_name(&name.name);
+                labels.insert(name.name.clone(), c_name);
+            }
+        }
+
+        self.labels.push(labels);
+        for stmt in &block.stmts {
+            self.statement(stmt);
+        }
+        self.labels.pop();
+    }
+
+    /**
+     * C name for a label, prefixed so names like continue or default aren't taken for C keywords.
+     * Labels of different blocks can share a name, the later ones are numbered, skipping numbers
+     * another label's name already ends in.
+     */
+    fn label_name(&mut self, name: &str) -> String {
+        let mut c_name = format!("lbl_{name}");
+        while ! self.label_names.insert(c_name.clone()) {
+            c_name = self.temp(&format!("lbl_{name}_"));
+        }
+
+        return c_name;
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> () {
+        match &stmt.kind {
+            StmtKind::Print(value) => {
+                if let ExprKind::String(bytes) = &value.kind {
+                    // Written with its length, so embedded '\0's are printed too.
+                    self.emit_line(format!("fwrite({}, 1, {}, stdout);", c_string(bytes), bytes.len()).as_str());
+                    self.emit_line("putchar('\\n');");
+                } else {
+                    let value = self.expression(value);
+                    self.emit_line(format!("printf(\"%.2f\\n\", lua_tofloat({value}));").as_str());
+                }
+            },
+
+            StmtKind::Input(name) => {
+                let name = self.declare(&name.name);
+                self.emit_line(format!("{name} = lua_read_number();").as_str());
+            },
+
+            StmtKind::Assign(targets, values) if targets.len() > 1 || values.len() > 1 => {
+                // The tables and keys, then the values, are all taken before anything is stored.
+                let targets: Vec<(String, Option<String>)> = targets.iter()
+                    .map(|target| match &target.kind {
+                        ExprKind::Index(table, key) => {
+                            let table = self.expression(table);
+                            let table = self.keep(table);
+                            let key = self.expression(key);
+                            (table, Some(self.keep(key)))
+                        },
+                        _ => (self.expression(target), None),
+                    })
+                    .collect();
+
+                let mut base = None;
+                let mut kept = Vec::new();
+                if values.last().is_some_and(|last| last.kind.is_multi_valued()) && targets.len() >= values.len() {
+                    let (list, results) = self.spread_values(values, targets.len());
+                    base = Some(list);
+                    kept = results;
+                } else {
+                    for (i, value) in values.iter().enumerate() {
+                        let value = self.expression(value);
+                        if i < targets.len() {
+                            kept.push(self.keep(value));
+                        } else {
+                            // Values without a target are still evaluated, for the errors they may raise.
+                            self.emit_line(format!("(void){value};").as_str());
+                        }
+                    }
+                    kept.resize(targets.len(), "lua_nil()".to_string());
+                }
+
+                for ((target, key), value) in targets.iter().zip(kept) {
+                    match key {
+                        Some(key) => self.emit_line(format!("lua_setindex({target}, {key}, {value});").as_str()),
+                        None => self.emit_line(format!("{target} = {value};").as_str()),
+                    }
+                }
+                if let Some(base) = base {
+                    self.emit_line(format!("lua_settop({base});").as_str());
+                }
+            },
+
+            StmtKind::Assign(targets, values) => {
+                let (target, value) = (&targets[0], &values[0]);
+                if let ExprKind::Index(table, key) = &target.kind {
+                    // The table and key are taken before the value is computed.
+                    let mut table = self.expression(table);
+                    let mut key = self.expression(key);
+                    if ! is_plain(value) {
+                        table = self.keep(table);
+                        key = self.keep(key);
+                    }
+
+                    let value = self.expression(value);
+                    self.emit_line(format!("lua_setindex({table}, {key}, {value});").as_str());
+                    return;
+                }
+
+                let value = self.expression(value);
+                let target = self.expression(target);
+                self.emit_line(format!("{target} = {value};").as_str());
+            },
+
+            StmtKind::Local(names, values) => {
+                // A call at the end gives the values of all the names left.
+                if values.last().is_some_and(|last| last.kind.is_multi_valued()) && names.len() >= values.len() {
+                    let (base, results) = self.spread_values(values, names.len());
+                    for ((name, id), value) in names.iter().zip(results) {
+                        self.declare_local(&name.name, *id, &value);
+                    }
+                    self.emit_line(format!("lua_settop({base});").as_str());
+                    return;
+                }
+
+                for (i, (name, id)) in names.iter().enumerate() {
+                    let value = match values.get(i) {
+                        Some(value) => self.expression(value),
+                        None => "lua_nil()".to_string(),
+                    };
+                    self.declare_local(&name.name, *id, &value);
+                }
+
+                // Values without a name are still evaluated, for the errors they may raise.
+                for value in values.iter().skip(names.len()) {
+                    let value = self.expression(value);
+                    self.emit_line(format!("(void){value};").as_str());
+                }
+            },
+
+            StmtKind::Do(body) => {
+                self.emit_line("{");
+                self.block(body);
+                self.emit_line("}");
+            },
+
+            StmtKind::LocalFunction(name, id, function) => {
+                if self.captured.contains(id) {
+                    // The function may use itself, so its cell has to exist before the closure.
+                    self.declare_local(&name.name, *id, "lua_nil()");
+                    let function = self.function(function);
+                    let local = self.local(&name.name, *id);
+                    self.emit_line(format!("{local} = {function};").as_str());
+                } else {
+                    let function = self.function(function);
+                    self.declare_local(&name.name, *id, &function);
+                }
+            },
+
+            StmtKind::Call(call) => {
+                let ExprKind::Call(function, args) = &call.kind else {
+                    unreachable!("the parser only makes statements of calls");
+                };
+
+                let base = self.push_call(function, args);
+                self.emit_line(format!("lua_call({base});").as_str());
+                self.emit_line(format!("lua_settop({base});").as_str());
+            },
+
+            StmtKind::Return(values) => {
+                if self.functions.is_empty() {
+                    // Returning from the main chunk ends the program.
+                    for value in values {
+                        let value = self.expression(value);
+                        self.emit_line(format!("(void){value};").as_str());
+                    }
+                    self.emit_line("return 0;");
+                } else if values.is_empty() {
+                    self.emit_line("return 0;");
+                } else {
+                    let base = self.value_list(values);
+                    self.emit_line(format!("return lua_return({base});").as_str());
+                }
+            },
+
+            StmtKind::If(condition, body, else_body) => {
+                let condition = self.expression(condition);
+                self.emit_line(format!("if (lua_truthy({condition})) {{").as_str());
+                self.block(body);
+                if let Some(else_body) = else_body {
+                    self.emit_line("} else {");
+                    self.block(else_body);
+                }
+                self.emit_line("}");
+            },
+
+            StmtKind::While(condition, body) => {
+                if is_plain(condition) {
+                    let condition = self.expression(condition);
+                    self.emit_line(format!("while (lua_truthy({condition})) {{").as_str());
+                } else {
+                    // The statements computing the condition have to run before each iteration.
+                    self.emit_line("while (1) {");
+                    let condition = self.expression(condition);
+                    self.emit_line(format!("if (! lua_truthy({condition})) break;").as_str());
+                }
+                self.block(body);
+                self.emit_line("}");
+            },
+
+            // The empty statement lets a declaration or the end of the block follow the label.
+            StmtKind::Label(name) => {
+                let label = self.labels.last().unwrap()[&name.name].clone();
+                self.emit_line(format!("{label}: ;").as_str());
+            },
+
+            StmtKind::Goto(name) => {
+                // The innermost visible label; BASIC labels are unique and reached from anywhere.
+                let label = match self.labels.iter().rev().find_map(|labels| labels.get(&name.name)) {
+                    Some(label) => label.clone(),
+                    None => format!("lbl_{}", name.name),
+                };
+                self.emit_line(format!("goto {label};").as_str());
+            },
+        }
+    }
+
+    /**
+     * C expression computing the Value of an expression. Every operator is a runtime call, so the
+     * nesting of the calls is the grouping of the tree and no parentheses are needed.
+     *
+     * Calls and the right side of "and" / "or" run in statements written before the expression, in
+     * the order Lua evaluates them; the expression then only reads their results.
+     */
+    fn expression(&mut self, expr: &Expr) -> String {
+        return match &expr.kind {
+            ExprKind::Nil => "lua_nil()".to_string(),
+            ExprKind::Boolean(value) => format!("lua_boolean({})", *value as u8),
+            ExprKind::Integer(value) => format!("lua_integer({})", integer_literal(*value)),
+            ExprKind::Float(value) => format!("lua_float({})", float_literal(*value)),
+            ExprKind::String(bytes) => format!("lua_string({}, {})", c_string(bytes), bytes.len()),
+            ExprKind::Variable(Variable::Global(name)) => {
+                // Lua can read globals that are never assigned.
+                self.declare(name)
+            },
+            ExprKind::Variable(Variable::Local(name, id)) => self.local(name, *id),
+            ExprKind::Unary(op, operand) => {
+                let operand = self.expression(operand);
+                format!("{}({operand})", unary_function(*op))
+            },
+            ExprKind::Binary(op @ (BinaryOp::And | BinaryOp::Or), left, right) => {
+                // The right side is only evaluated if the left one doesn't decide the result.
+                let left = self.expression(left);
+                let result = self.temp("t");
+                self.emit_line(format!("Value {result} = {left};").as_str());
+
+                let negation = if *op == BinaryOp::Or { "! " } else { "" };
+                self.emit_line(format!("if ({negation}lua_truthy({result})) {{").as_str());
+                let right = self.expression(right);
+                self.emit_line(format!("{result} = {right};").as_str());
+                self.emit_line("}");
+
+                result
+            },
+            ExprKind::Binary(op, left, right) => {
+                // The left side is read before anything the right one runs can change it.
+                let mut left = self.expression(left);
+                if ! is_plain(right) {
+                    left = self.keep(left);
+                }
+                let right = self.expression(right);
+                format!("{}({left}, {right})", binary_function(*op))
+            },
+            ExprKind::Function(function) => self.function(function),
+            ExprKind::Call(function, args) => {
+                let base = self.push_call(function, args);
+                let result = self.temp("t");
+                self.emit_line(format!("Value {result} = lua_call1({base});").as_str());
+                result
+            },
+            ExprKind::Paren(inner) => self.expression(inner),
+            ExprKind::Table(fields) => self.table(fields),
+            ExprKind::Index(table, key) => {
+                let mut table = self.expression(table);
+                if ! is_plain(key) {
+                    table = self.keep(table);
+                }
+                let key = self.expression(key);
+                format!("lua_index({table}, {key})")
+            },
+        };
+    }
+
+    /**
+     * Build a table in a new C variable, storing its fields in order.
+     */
+    fn table(&mut self, fields: &[Field]) -> String {
+        let table = self.temp("t");
+        self.emit_line(format!("Value {table} = lua_table();").as_str());
+
+        let mut position = 0;
+        for (i, field) in fields.iter().enumerate() {
+            match field {
+                // All the results of a call at the end take the positions left.
+                Field::Positional(Expr { kind: ExprKind::Call(function, args), .. }) if i == fields.len() - 1 => {
+                    let base = self.push_call(function, args);
+                    self.emit_line(format!("lua_call({base});").as_str());
+                    self.emit_line(format!("lua_setlist({table}, {}, {base});", position + 1).as_str());
+                },
+                Field::Positional(value) => {
+                    position += 1;
+                    let value = self.expression(value);
+                    self.emit_line(format!("lua_setindex({table}, lua_integer({}), {value});", integer_literal(position)).as_str());
+                },
+                Field::Keyed(key, value) => {
+                    let key = self.expression(key);
+                    let value = self.expression(value);
+                    self.emit_line(format!("lua_setindex({table}, {key}, {value});").as_str());
+                },
+            }
+        }
+
+        return table;
+    }
+
+    /**
+     * Copy a value to a new C variable, so what runs next can't change it.
+     */
+    fn keep(&mut self, value: String) -> String {
+        let kept = self.temp("t");
+        self.emit_line(format!("Value {kept} = {value};").as_str());
+        return kept;
+    }
+
+    /**
+     * Push a function and its arguments on the runtime's stack, giving back the C variable holding
+     * where they start.
+     */
+    fn push_call(&mut self, function: &Expr, args: &[Expr]) -> String {
+        let base = self.temp("base");
+        self.emit_line(format!("const int {base} = lua_top;").as_str());
+
+        let function = self.expression(function);
+        self.emit_line(format!("lua_push({function});").as_str());
+        self.push_values(args);
+
+        return base;
+    }
+
+    /**
+     * Push a list of values on the runtime's stack, giving back the C variable holding where it starts.
+     */
+    fn value_list(&mut self, values: &[Expr]) -> String {
+        let base = self.temp("base");
+        self.emit_line(format!("const int {base} = lua_top;").as_str());
+        self.push_values(values);
+
+        return base;
+    }
+
+    /**
+     * Push a list of values ending with a call, giving back where it starts and C expressions for
+     * its first count values, nil past its end. They're valid until the stack is reset to the start.
+     */
+    fn spread_values(&mut self, values: &[Expr], count: usize) -> (String, Vec<String>) {
+        let base = self.value_list(values);
+        let values = (0..count).map(|i| format!("lua_get({base}, {i})")).collect();
+
+        return (base, values);
+    }
+
+    fn push_values(&mut self, values: &[Expr]) -> () {
+        for (i, value) in values.iter().enumerate() {
+            match &value.kind {
+                // The results of a call at the end of the list are left where it was, all of them.
+                ExprKind::Call(function, args) if i == values.len() - 1 => {
+                    let base = self.push_call(function, args);
+                    self.emit_line(format!("lua_call({base});").as_str());
+                },
+                _ => {
+                    let value = self.expression(value);
+                    self.emit_line(format!("lua_push({value});").as_str());
+                },
+            }
+        }
+    }
+
+    /**
+     * Write a function as a C function of its own, giving back the expression creating its closure.
+     */
+    fn function(&mut self, function: &Function) -> String {
+        self.function_count += 1;
+        let c_name = match &function.name {
+            Some(name) => format!("f{}_{name}", self.function_count),
+            None => format!("f{}", self.function_count),
+        };
+
+        // The cells are taken where the function is defined, before its own locals exist.
+        let upvalues = free_locals(function);
+        let mut closure = format!("lua_closure({c_name}, {}", upvalues.len());
+        for (name, id) in &upvalues {
+            closure += &format!(", {}", self.cell(name, *id));
+        }
+        closure += ")";
+
+        self.functions.push(String::new());
+        self.upvalues.push(upvalues);
+        let labels = std::mem::take(&mut self.labels);
+        let label_names = std::mem::take(&mut self.label_names);
+        self.emit_line(format!("static int {c_name}(Closure *closure, int argc, Value *argv) {{").as_str());
+
+        for (i, (param, id)) in function.params.iter().enumerate() {
+            self.declare_local(&param.name, *id, &format!("lua_arg(argc, argv, {i})"));
+        }
+        self.block(&function.body);
+
+        self.emit_line("return 0;");
+        self.emit_line("}");
+
+        self.labels = labels;
+        self.label_names = label_names;
+        self.upvalues.pop();
+        let code = self.functions.pop().unwrap();
+        self.emitter.header_line(code.trim_end());
+
+        return closure;
+    }
+
+    /**
+     * Declare a local with its first value, in a cell if a closure uses it.
+     */
+    fn declare_local(&mut self, name: &str, id: LocalId, value: &str) -> () {
+        let c_name = local_name(name, id);
+
+        if self.captured.contains(&id) {
+            self.emit_line(format!("Value *{c_name} = lua_cell({value});").as_str());
+        } else {
+            self.emit_line(format!("Value {c_name} = {value};").as_str());
+        }
+    }
+
+    /**
+     * C expression for a local, read or assigned.
+     */
+    fn local(&self, name: &str, id: LocalId) -> String {
+        if self.captured.contains(&id) {
+            return format!("(*{})", self.cell(name, id));
+        }
+
+        return local_name(name, id);
+    }
+
+    /**
+     * Pointer to the cell of a captured local: one of the closure's own cells if the local belongs
+     * to an enclosing function.
+     */
+    fn cell(&self, name: &str, id: LocalId) -> String {
+        if let Some(upvalues) = self.upvalues.last() {
+            if let Some(index) = upvalues.iter().position(|(_, upvalue)| *upvalue == id) {
+                return format!("closure->upvalues[{index}]");
+            }
+        }
+
+        return local_name(name, id);
+    }
+
+    /**
+     * Declare a global the first time it is used, and give back its C name. Globals are static so
+     * they start out zeroed, which is nil.
+     */
+    fn declare(&mut self, name: &str) -> String {
+        let c_name = format!("g_{name}");
+
+        if ! self.declared.contains(name) {
+            self.declared.insert(name.to_string());
+
+            match BUILTINS.iter().find(|(builtin, _)| *builtin == name) {
+                Some((_, value)) => self.emitter.header_line(format!("static Value {c_name} = {value};").as_str()),
+                None => self.emitter.header_line(format!("static Value {c_name};").as_str()),
+            }
+        }
+
+        return c_name;
+    }
+}
+
+/**
+ * Whether an expression is computed without any statements before it, see Generator::expression.
+ */
+fn is_plain(expr: &Expr) -> bool {
+    return match &expr.kind {
+        ExprKind::Binary(BinaryOp::And | BinaryOp::Or, ..) | ExprKind::Call(..) => false,
+        ExprKind::Unary(_, operand) | ExprKind::Paren(operand) => is_plain(operand),
+        ExprKind::Binary(_, left, right) | ExprKind::Index(left, right) => is_plain(left) && is_plain(right),
+        ExprKind::Table(_) => false,
+        _ => true,
+    };
+}
+
+/**
+ * C name of a local, numbered so locals shadowing each other in nested C blocks stay apart.
+ */
+fn local_name(name: &str, id: LocalId) -> String {
+    return format!("l_{name}_{id}");
+}
+
+fn unary_function(op: UnaryOp) -> &'static str {
+    return match op {
+        UnaryOp::Neg => "lua_unm",
+        UnaryOp::Not => "lua_not",
+        UnaryOp::Len => "lua_len",
+        UnaryOp::BNot => "lua_bnot",
+    };
+}
+
+fn binary_function(op: BinaryOp) -> &'static str {
+    return match op {
+        BinaryOp::Or | BinaryOp::And => unreachable!("and / or don't always evaluate their right side"),
+        BinaryOp::BOr => "lua_bor",
+        BinaryOp::BXor => "lua_bxor",
+        BinaryOp::BAnd => "lua_band",
+        BinaryOp::Shl => "lua_shl",
+        BinaryOp::Shr => "lua_shr",
+        BinaryOp::Concat => "lua_concat",
+        BinaryOp::Add => "lua_add",
+        BinaryOp::Sub => "lua_sub",
+        BinaryOp::Mul => "lua_mul",
+        BinaryOp::Div => "lua_div",
+        BinaryOp::IDiv => "lua_idiv",
+        BinaryOp::Mod => "lua_mod",
+        BinaryOp::Pow => "lua_pow",
+        BinaryOp::Eq => "lua_eq",
+        BinaryOp::NotEq => "lua_ne",
+        BinaryOp::Lt => "lua_lt",
+        BinaryOp::LtEq => "lua_le",
+        BinaryOp::Gt => "lua_gt",
+        BinaryOp::GtEq => "lua_ge",
+    };
+}
+
+/**
+ * C spelling of an integer constant; the smallest one has no literal as its minus is an operator in C.
+ */
+fn integer_literal(value: i64) -> String {
+    if value == i64::MIN {
+        return "INT64_MIN".to_string();
+    }
+
+    return format!("INT64_C({value})");
+}
+
+/**
+ * C spelling of a float constant; Rust's debug format always keeps a '.' or an exponent.
+ */
+fn float_literal(value: f64) -> String {
+    if value.is_infinite() {
+        return "HUGE_VAL".to_string();
+    }
+
+    return format!("{value:?}");
+}
+
+/**
+ * C string literal holding exactly the given bytes. Only plain printable chars are copied,
+ * everything else becomes an octal escape so no value can end the literal early.
+ */
+fn c_string(bytes: &[u8]) -> String {
+    let mut out = String::from("\"");
+
+    let mut previous = 0;
+    for &byte in bytes {
+        match byte {
+            b'"' => out += "\\\"",
+            b'\\' => out += "\\\\",
+            // Keep "??x" from being read as a trigraph.
+            b'?' if previous == b'?' => out += "\\?",
+            b'\n' => out += "\\n",
+            b'\t' => out += "\\t",
+            0x20..=0x7E => out.push(byte as char),
+            _ => out += &format!("\\{byte:03o}"),
+        }
+        previous = byte;
+    }
+
+    out.push('"');
+    return out;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::Dialect;
+    use crate::{compile, CompileOptions, Output};
+
+    fn generate(source: &str) -> String {
+        let options = CompileOptions { dialect: Dialect::Lua, ..CompileOptions::default() };
+        let Ok(Output::C(emitter)) = compile(source, &options) else {
+            panic!("{source:?} should compile");
+        };
+
+        return emitter.output();
+    }
+
+    #[test]
+    fn left_operand_is_read_before_a_call_on_the_right() {
+        let c = generate("local x = 1\nprint(x + f())");
+
+        assert!(c.contains("Value t2 = l_x_0;\nconst int base3 = lua_top;\nlua_push(g_f);\nValue t4 = lua_call1(base3);\nlua_push(lua_add(t2, t4));"), "{c}");
+    }
+
+    #[test]
+    fn table_is_read_before_a_call_in_the_key() {
+        let c = generate("local t = {}\nprint(t[f()])");
+
+        assert!(c.contains("Value t3 = l_t_0;\nconst int base4 = lua_top;\nlua_push(g_f);\nValue t5 = lua_call1(base4);\nlua_push(lua_index(t3, t5));"), "{c}");
+    }
+
+    #[test]
+    fn labels_named_like_c_keywords_are_renamed() {
+        let c = generate("while true do goto continue ::continue:: end");
+
+        assert!(c.contains("goto lbl_continue;\nlbl_continue: ;"), "{c}");
+    }
+
+    #[test]
+    fn sibling_labels_get_their_own_c_labels() {
+        let c = generate("while a do goto continue ::continue:: end\nwhile b do goto continue ::continue:: end");
+
+        assert!(c.contains("goto lbl_continue;\nlbl_continue: ;"), "{c}");
+        assert!(c.contains("goto lbl_continue_"), "{c}");
+        assert_eq!(c.matches("lbl_continue: ;").count(), 1, "{c}");
+    }
+
+    #[test]
+    fn numbered_labels_skip_names_already_taken() {
+        let c = generate("do ::x:: end do ::x:: end ::x_1::");
+
+        assert_eq!(c.matches("lbl_x_1: ;").count(), 1, "{c}");
+        assert_eq!(c.matches("lbl_x: ;").count(), 1, "{c}");
+    }
+
+    #[test]
+    fn goto_jumps_to_the_innermost_visible_label() {
+        let c = generate("do goto l ::l:: end\n::l::");
+
+        assert!(c.contains("goto lbl_l_1;\nlbl_l_1: ;\n}\nlbl_l: ;"), "{c}");
+    }
+
+    #[test]
+    fn strings_are_escaped_for_c() {
+        let c = generate(r#"print("a\0b\"\\\n\200??=")"#);
+
+        assert!(c.contains(r#"lua_string("a\000b\"\\\n\310?\?=", 10)"#), "{c}");
+    }
+
+    #[test]
+    fn parenthesized_call_gives_one_value() {
+        let c = generate("print((f()))\nprint(f())");
+
+        assert!(c.contains("lua_push(g_f);\nValue t3 = lua_call1(base2);\nlua_push(t3);\nlua_call(base1);"), "{c}");
+        assert!(c.contains("lua_push(g_f);\nlua_call(base5);\nlua_call(base4);"), "{c}");
+    }
+
+    #[test]
+    fn closures_share_a_captured_local() {
+        let c = generate("local n = 0\nlocal function inc() n = n + 1 end\nlocal function get() return n end");
+
+        // One cell, handed to both closures, and written and read through it.
+        assert!(c.contains("Value *l_n_0 = lua_cell(lua_integer(INT64_C(0)));\nValue l_inc_1 = lua_closure(f1_inc, 1, l_n_0);\nValue l_get_2 = lua_closure(f2_get, 1, l_n_0);"), "{c}");
+        assert!(c.contains("(*closure->upvalues[0]) = lua_add((*closure->upvalues[0]), lua_integer(INT64_C(1)));"), "{c}");
+        assert!(c.contains("lua_push((*closure->upvalues[0]));\nreturn lua_return(base1);"), "{c}");
+    }
+
+    #[test]
+    fn local_function_captures_itself() {
+        let c = generate("local function fact(k) if k < 2 then return 1 end return k * fact(k - 1) end");
+
+        // The cell exists before the closure, which is then stored in it.
+        assert!(c.contains("Value *l_fact_0 = lua_cell(lua_nil());\n(*l_fact_0) = lua_closure(f1_fact, 1, l_fact_0);"), "{c}");
+        assert!(c.contains("lua_push((*closure->upvalues[0]));\nlua_push(lua_sub(l_k_1, lua_integer(INT64_C(1))));"), "{c}");
+    }
+
+    #[test]
+    fn captured_local_in_a_loop_gets_a_cell_per_iteration() {
+        let c = generate("local fs = {}\nlocal i = 0\nwhile i < 3 do i = i + 1 local j = i fs[i] = function() return j end end");
+
+        // Made in the body, so each closure keeps the cell of its own iteration.
+        assert!(c.contains("{\nl_i_1 = lua_add(l_i_1, lua_integer(INT64_C(1)));\nValue *l_j_2 = lua_cell(l_i_1);\nlua_setindex(l_fs_0, l_i_1, lua_closure(f1, 1, l_j_2));\n}"), "{c}");
+        // Only captured locals are kept in cells.
+        assert!(c.contains("Value l_i_1 = lua_integer(INT64_C(0));"), "{c}");
+    }
+
+    #[test]
+    fn constructor_numbers_only_positional_fields() {
+        let c = generate("local t = {10, k = 20, 30}");
+        assert!(c.contains("lua_setindex(t1, lua_integer(INT64_C(1)), lua_integer(INT64_C(10)));\nlua_setindex(t1, lua_string(\"k\", 1), lua_integer(INT64_C(20)));\nlua_setindex(t1, lua_integer(INT64_C(2)), lua_integer(INT64_C(30)));"), "{c}");
+    }
+
+    #[test]
+    fn trailing_call_in_constructor_fills_the_array_part() {
+        let c = generate("local t = {10, f(), f()}");
+
+        // Only the last call keeps all its results, from the position after the fields before it.
+        assert!(c.contains("lua_setindex(t1, lua_integer(INT64_C(2)), t3);"), "{c}");
+        assert!(c.contains("lua_call(base4);\nlua_setlist(t1, 3, base4);"), "{c}");
+    }
+
+    #[test]
+    fn fields_are_assigned_through_setindex() {
+        let c = generate("local t = {}\nt.x = 1\nt[k] = 2");
+        assert!(c.contains("lua_setindex(l_t_0, lua_string(\"x\", 1), lua_integer(INT64_C(1)));"), "{c}");
+        assert!(c.contains("lua_setindex(l_t_0, g_k, lua_integer(INT64_C(2)));"), "{c}");
+    }
+
+    #[test]
+    fn table_insert_is_a_library_call() {
+        let c = generate("local t = {}\ntable.insert(t, 1)");
+        assert!(c.contains("lua_push(lua_index(g_table, lua_string(\"insert\", 6)));\nlua_push(l_t_0);\nlua_push(lua_integer(INT64_C(1)));\nlua_call(base2);"), "{c}");
+    }
+
+    #[test]
+    fn multiple_assignment_computes_every_value_first() {
+        let c = generate("local a, b = 1, 2\na, b = b, a");
+        assert!(c.contains("Value t1 = l_b_1;\nValue t2 = l_a_0;\nl_a_0 = t1;\nl_b_1 = t2;"), "{c}");
+    }
+
+    #[test]
+    fn multiple_assignment_spreads_a_trailing_call() {
+        let c = generate("local t = {}\nlocal a\na, t.x, t[a] = f()");
+
+        // The field's key is taken before a is assigned.
+        assert!(c.contains("Value t4 = l_t_0;\nValue t5 = l_a_1;\n"), "{c}");
+        assert!(c.contains("lua_call(base7);\nl_a_1 = lua_get(base6, 0);\nlua_setindex(t2, t3, lua_get(base6, 1));\nlua_setindex(t4, t5, lua_get(base6, 2));\nlua_settop(base6);"), "{c}");
+    }
+
+    #[test]
+    fn multiple_assignment_fills_with_nil_and_drops_extra_values() {
+        let c = generate("a, b = 1\na = 1, g()");
+        assert!(c.contains("Value t1 = lua_integer(INT64_C(1));\ng_a = t1;\ng_b = lua_nil();"), "{c}");
+        assert!(c.contains("Value t2 = lua_integer(INT64_C(1));\nconst int base3 = lua_top;\nlua_push(g_g);\nValue t4 = lua_call1(base3);\n(void)t4;\ng_a = t2;"), "{c}");
+    }
+
+    #[test]
+    fn plain_operands_are_not_spilled() {
+        let c = generate("local x = 1\nprint(x + 2)");
+
+        assert!(c.contains("lua_push(lua_add(l_x_0, lua_integer(INT64_C(2))));"), "{c}");
+    }
+}
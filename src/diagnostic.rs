@@ -0,0 +1,472 @@
+use std::fmt;
+
+use crate::span::Span;
+
+/**
+ * Stable code identifying each kind of diagnostic, shown as E0001, E0100, ...
+ */
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+pub enum ErrorCode {
+    // Lexical errors.
+    UnknownToken = 1,
+    IllegalString = 2,
+    IllegalNumber = 3,
+    InvalidEscape = 4,
+
+    // Syntax errors.
+    UnexpectedToken = 100,
+    InvalidStatement = 101,
+    TooManyErrors = 102,
+
+    // Semantic errors.
+    UndeclaredVariable = 200,
+    DuplicateLabel = 201,
+    UndeclaredLabel = 202,
+    JumpIntoScope = 203,
+}
+
+impl ErrorCode {
+    pub fn severity(&self) -> Severity {
+        return match self {
+            ErrorCode::TooManyErrors => Severity::Note,
+            _ => Severity::Error,
+        };
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "E{:04}", *self as i32)
+    }
+}
+
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+pub enum Severity {
+    Error,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/**
+ * Secondary location attached to a diagnostic, e.g. where a label was first declared.
+ */
+#[derive(Clone, Debug)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(span: Span, message: String) -> Self {
+        return Label { span, message };
+    }
+}
+
+/**
+ * A message about the source code, pointing at the span that caused it.
+ */
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: ErrorCode,
+    pub message: String,
+    pub span: Span,
+    pub labels: Vec<Label>,
+}
+
+impl Diagnostic {
+    pub fn new(code: ErrorCode, message: String, span: Span, labels: Vec<Label>) -> Self {
+        return Diagnostic { severity: code.severity(), code, message, span, labels };
+    }
+}
+
+/**
+ * Every diagnostic produced while compiling one source file.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Diagnostics {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new(diagnostics: Vec<Diagnostic>) -> Self {
+        return Diagnostics { diagnostics };
+    }
+
+    pub fn error_count(&self) -> usize {
+        return self.diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Error).count();
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        return self.diagnostics.iter();
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{}: {}[{}]: {}", diagnostic.span, diagnostic.severity, diagnostic.code, diagnostic.message)?;
+        }
+
+        return Ok(());
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+/**
+ * How diagnostics are printed: for people, as JSON lines, or as a SARIF log.
+ */
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+    Sarif,
+}
+
+impl TryFrom<&str> for ErrorFormat {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        return match value {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            "sarif" => Ok(ErrorFormat::Sarif),
+            _ => Err(format!("Unknown error format: {value} (expected human, json or sarif)")),
+        };
+    }
+}
+
+// ANSI escape codes used when rendering to a terminal.
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const GREEN: &str = "\x1b[1;32m";
+const BLUE: &str = "\x1b[1;34m";
+
+/**
+ * Renders diagnostics rustc-style, with the offending source lines and carets under the spans,
+ * or as JSON / SARIF for tools.
+ */
+pub struct Renderer<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+    color: bool,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(source: &'a str, color: bool) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(source.match_indices('\n').map(|(index, _)| index + 1));
+
+        return Renderer { source, line_starts, color };
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let severity_color = match diagnostic.severity {
+            Severity::Error => RED,
+            Severity::Note => GREEN,
+        };
+
+        // Primary span first, then the secondary labels in source order.
+        let mut annotations = vec![(&diagnostic.span, '^', severity_color, "")];
+        for label in &diagnostic.labels {
+            annotations.push((&label.span, '-', BLUE, label.message.as_str()));
+        }
+        annotations.sort_by_key(|(span, _, _, _)| span.start);
+
+        let last_line = annotations.iter()
+            .map(|(span, _, _, _)| self.line_index(span.end.max(span.start + 1) - 1) + 1)
+            .max()
+            .unwrap_or(1);
+        let gutter = " ".repeat(last_line.to_string().len());
+
+        let mut out = format!(
+            "{}{}[{}]{}: {}{}{}\n",
+            self.paint(severity_color), diagnostic.severity, diagnostic.code, self.paint(RESET),
+            self.paint(BOLD), diagnostic.message, self.paint(RESET),
+        );
+        out += &format!("{gutter}{}-->{} {}\n", self.paint(BLUE), self.paint(RESET), diagnostic.span);
+        out += &format!("{gutter} {}|{}\n", self.paint(BLUE), self.paint(RESET));
+
+        let mut previous_line: Option<usize> = None;
+        for (span, marker, color, message) in annotations {
+            let first = self.line_index(span.start);
+            let last = self.line_index(span.end.max(span.start + 1) - 1);
+
+            for line in first..=last {
+                if let Some(previous) = previous_line {
+                    if line > previous + 1 {
+                        out += &format!("{}...{}\n", self.paint(BLUE), self.paint(RESET));
+                    }
+                }
+
+                let (line_start, line_end) = self.line_bounds(line);
+                let text = &self.source[line_start..line_end];
+
+                if previous_line != Some(line) {
+                    out += &format!(
+                        "{}{:>width$} |{} {}\n",
+                        self.paint(BLUE), line + 1, self.paint(RESET), expand_tabs(text),
+                        width = gutter.len(),
+                    );
+                }
+
+                // Underline the part of the span on this line, always at least one char wide.
+                let from = span.start.clamp(line_start, line_end) - line_start;
+                let to = span.end.clamp(line_start, line_end) - line_start;
+                let padding = expand_tabs(&text[..from]).chars().count();
+                let width = expand_tabs(&text[from..to.max(from)]).chars().count().max(1);
+                let note = if line == last && ! message.is_empty() { format!(" {message}") } else { String::new() };
+
+                out += &format!(
+                    "{gutter} {}|{} {}{}{}{}{}\n",
+                    self.paint(BLUE), self.paint(RESET), " ".repeat(padding),
+                    self.paint(color), marker.to_string().repeat(width), note, self.paint(RESET),
+                );
+
+                previous_line = Some(line);
+            }
+        }
+
+        return out;
+    }
+
+    /**
+     * Render every diagnostic in the given format.
+     */
+    pub fn render_all(&self, diagnostics: &[Diagnostic], format: ErrorFormat) -> String {
+        return match format {
+            ErrorFormat::Human => diagnostics.iter().map(|diagnostic| self.render(diagnostic) + "\n").collect(),
+            ErrorFormat::Json => diagnostics.iter().map(|diagnostic| self.render_json(diagnostic) + "\n").collect(),
+            ErrorFormat::Sarif => self.render_sarif(diagnostics) + "\n",
+        };
+    }
+
+    /**
+     * One JSON object per diagnostic, with the span and any related spans.
+     */
+    pub fn render_json(&self, diagnostic: &Diagnostic) -> String {
+        let related: Vec<String> = diagnostic.labels.iter()
+            .map(|label| format!("{{\"message\":{},\"span\":{}}}", json_string(&label.message), self.json_span(&label.span)))
+            .collect();
+
+        return format!(
+            "{{\"code\":{},\"severity\":{},\"message\":{},\"span\":{},\"related\":[{}]}}",
+            json_string(&diagnostic.code.to_string()), json_string(&diagnostic.severity.to_string()),
+            json_string(&diagnostic.message), self.json_span(&diagnostic.span), related.join(","),
+        );
+    }
+
+    /**
+     * A SARIF 2.1.0 log with one result per diagnostic.
+     */
+    pub fn render_sarif(&self, diagnostics: &[Diagnostic]) -> String {
+        return render_sarif_files(&[(self, diagnostics)]);
+    }
+
+    fn sarif_result(&self, diagnostic: &Diagnostic) -> String {
+        let related: Vec<String> = diagnostic.labels.iter().enumerate()
+            .map(|(id, label)| format!(
+                "{{\"id\":{id},\"message\":{{\"text\":{}}},\"physicalLocation\":{}}}",
+                json_string(&label.message), self.sarif_location(&label.span),
+            ))
+            .collect();
+
+        return format!(
+            "{{\"ruleId\":{},\"level\":{},\"message\":{{\"text\":{}}},\"locations\":[{{\"physicalLocation\":{}}}],\"relatedLocations\":[{}]}}",
+            json_string(&diagnostic.code.to_string()), json_string(&diagnostic.severity.to_string()),
+            json_string(&diagnostic.message), self.sarif_location(&diagnostic.span), related.join(","),
+        );
+    }
+
+    fn json_span(&self, span: &Span) -> String {
+        let (end_line, end_column) = self.location(span.end);
+        return format!(
+            "{{\"file\":{},\"line\":{},\"column\":{},\"end_line\":{end_line},\"end_column\":{end_column},\"byte_start\":{},\"byte_end\":{}}}",
+            json_string(&span.file), span.line, span.column, span.start, span.end,
+        );
+    }
+
+    fn sarif_location(&self, span: &Span) -> String {
+        let (end_line, end_column) = self.location(span.end);
+        return format!(
+            "{{\"artifactLocation\":{{\"uri\":{}}},\"region\":{{\"startLine\":{},\"startColumn\":{},\"endLine\":{end_line},\"endColumn\":{end_column}}}}}",
+            json_string(span.file.trim_start_matches("./")), span.line, span.column,
+        );
+    }
+
+    /**
+     * One-based line and column of a byte offset.
+     */
+    fn location(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.source.len());
+        let line = self.line_index(offset);
+        let line_start = self.line_bounds(line).0.min(offset);
+
+        return (line + 1, self.source[line_start..offset].chars().count() + 1);
+    }
+
+    /**
+     * Zero-based index of the line containing the byte offset.
+     */
+    fn line_index(&self, offset: usize) -> usize {
+        let offset = offset.min(self.source.len());
+        return match self.line_starts.binary_search(&offset) {
+            Ok(index) => index,
+            Err(index) => index - 1,
+        };
+    }
+
+    /**
+     * Byte range of a line, without its line break. A leading byte order mark isn't part of the
+     * first line, the lexer doesn't count it as a column either.
+     */
+    fn line_bounds(&self, line: usize) -> (usize, usize) {
+        let start = self.line_starts[line].min(self.source.len());
+        let start = if start == 0 && self.source.starts_with('\u{FEFF}') { '\u{FEFF}'.len_utf8() } else { start };
+        let end = self.line_starts.get(line + 1).map_or(self.source.len(), |next| next - 1);
+        let end = if self.source[start..end].ends_with('\r') { end - 1 } else { end };
+
+        return (start, end.max(start));
+    }
+
+    fn paint(&self, code: &'static str) -> &'static str {
+        if self.color {
+            return code;
+        }
+
+        return "";
+    }
+}
+
+/**
+ * A SARIF 2.1.0 log with a single run holding the diagnostics of every file, each located
+ * against its own source.
+ */
+pub fn render_sarif_files(files: &[(&Renderer, &[Diagnostic])]) -> String {
+    let mut codes: Vec<String> = files.iter()
+        .flat_map(|(_, diagnostics)| diagnostics.iter().map(|diagnostic| diagnostic.code.to_string()))
+        .collect();
+    codes.sort();
+    codes.dedup();
+    let rules: Vec<String> = codes.iter().map(|code| format!("{{\"id\":{}}}", json_string(code))).collect();
+
+    let results: Vec<String> = files.iter()
+        .flat_map(|(renderer, diagnostics)| diagnostics.iter().map(|diagnostic| renderer.sarif_result(diagnostic)))
+        .collect();
+
+    return format!(
+        "{{\"$schema\":\"https://json.schemastore.org/sarif-2.1.0.json\",\"version\":\"2.1.0\",\"runs\":[{{\"tool\":{{\"driver\":{{\"name\":{},\"version\":{},\"rules\":[{}]}}}},\"results\":[{}]}}]}}",
+        json_string(env!("CARGO_PKG_NAME")), json_string(env!("CARGO_PKG_VERSION")), rules.join(","), results.join(","),
+    );
+}
+
+fn expand_tabs(text: &str) -> String {
+    return text.replace('\t', "    ");
+}
+
+/**
+ * Quote and escape a string as a JSON string literal.
+ */
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            '\r' => out += "\\r",
+            '\t' => out += "\\t",
+            c if (c as u32) < 0x20 => out += &format!("\\u{:04x}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+
+    return out;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Diagnostic, ErrorFormat, Renderer};
+    use crate::lexer::Dialect;
+    use crate::{compile, CompileOptions, Emit};
+
+    fn diagnostics(source: &str) -> Vec<Diagnostic> {
+        let options = CompileOptions { dialect: Dialect::Lua, emit: Emit::Ast, ..CompileOptions::default() };
+        let Err(diagnostics) = compile(source, &options) else {
+            panic!("{source:?} should not compile");
+        };
+
+        return diagnostics.diagnostics;
+    }
+
+    fn render(source: &str, format: ErrorFormat) -> String {
+        return Renderer::new(source, false).render_all(&diagnostics(source), format);
+    }
+
+    #[test]
+    fn json_has_one_object_per_diagnostic() {
+        let out = render("local x = =\nlocal function f() goto nowhere end", ErrorFormat::Json);
+        let lines: Vec<serde_json::Value> = out.lines().map(|line| serde_json::from_str(line).expect("should be JSON")).collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["code"], "E0100");
+        assert_eq!(lines[0]["severity"], "error");
+        assert_eq!(lines[0]["span"]["file"], "<input>");
+        assert_eq!((&lines[0]["span"]["line"], &lines[0]["span"]["column"], &lines[0]["span"]["end_column"]), (&1.into(), &11.into(), &12.into()));
+        assert_eq!(lines[1]["code"], "E0202");
+        assert_eq!((&lines[1]["span"]["line"], &lines[1]["span"]["column"], &lines[1]["span"]["end_column"]), (&2.into(), &25.into(), &32.into()));
+        assert!(lines[1]["related"].is_array());
+    }
+
+    #[test]
+    fn sarif_has_one_result_per_diagnostic() {
+        let out = render("local x = =\nlocal function f() goto nowhere end", ErrorFormat::Sarif);
+        let log: serde_json::Value = serde_json::from_str(&out).expect("should be JSON");
+
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], env!("CARGO_PKG_NAME"));
+        let rules: Vec<&str> = run["tool"]["driver"]["rules"].as_array().unwrap().iter().map(|rule| rule["id"].as_str().unwrap()).collect();
+        assert_eq!(rules, vec!["E0100", "E0202"]);
+
+        let results = run["results"].as_array().unwrap();
+        let rule_ids: Vec<&str> = results.iter().map(|result| result["ruleId"].as_str().unwrap()).collect();
+        assert_eq!(rule_ids, vec!["E0100", "E0202"]);
+        assert_eq!(results[0]["level"], "error");
+
+        let region = &results[1]["locations"][0]["physicalLocation"]["region"];
+        assert_eq!((&region["startLine"], &region["startColumn"], &region["endColumn"]), (&2.into(), &25.into(), &32.into()));
+    }
+
+    #[test]
+    fn caret_ignores_byte_order_mark() {
+        let out = render("\u{FEFF}x = = 1", ErrorFormat::Human);
+
+        assert!(out.contains("--> <input>:1:5\n"), "{out}");
+        assert!(out.contains("1 | x = = 1\n  |     ^\n"), "{out}");
+    }
+
+    #[test]
+    fn json_columns_ignore_byte_order_mark() {
+        let out = render("\u{FEFF}x = = 1", ErrorFormat::Json);
+
+        assert!(out.contains("\"line\":1,\"column\":5,\"end_line\":1,\"end_column\":6,"), "{out}");
+    }
+}
@@ -1,16 +1,16 @@
-use std::fs;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
 
 pub struct Emitter {
-    full_path: String,
     header: String,
     code: String,
 }
 
 impl Emitter {
 
-    pub fn new(full_path: &str) -> Self {
+    pub fn new() -> Self {
         return Emitter {
-            full_path: full_path.to_string(),
             header: String::new(),
             code: String::new(),
         };
@@ -28,7 +28,97 @@ impl Emitter {
         self.header += &format!("{code}\n");
     }
 
-    pub fn write_file(&self) {
-        let _ = fs::write(self.full_path.clone(), self.header.clone() + &self.code);
+    /**
+     * The complete generated code, header first.
+     */
+    pub fn output(&self) -> String {
+        return self.header.clone() + &self.code;
     }
-}
\ No newline at end of file
+
+    /**
+     * Write the generated code to any sink: a file, stdout, a Vec<u8>...
+     */
+    pub fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(self.header.as_bytes())?;
+        out.write_all(self.code.as_bytes())?;
+        return out.flush();
+    }
+
+    pub fn write_file(&self, full_path: &str, create_dirs: bool) -> io::Result<()> {
+        let mut file = create_file(full_path, create_dirs)?;
+        return self.write_to(&mut file);
+    }
+}
+
+impl Default for Emitter {
+    fn default() -> Self {
+        return Emitter::new();
+    }
+}
+
+/**
+ * Create (or truncate) a file, first creating its missing parent directories if asked to.
+ */
+pub fn create_file(full_path: &str, create_dirs: bool) -> io::Result<File> {
+    if create_dirs {
+        if let Some(parent) = Path::new(full_path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+
+    return File::create(full_path);
+}
+#[cfg(test)]
+mod tests {
+    use super::Emitter;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /**
+     * A fresh directory for one test's files.
+     */
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lua-compiler-emitter-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        return dir;
+    }
+
+    fn emitter() -> Emitter {
+        let mut emitter = Emitter::new();
+        emitter.header_line("#include <stdio.h>");
+        emitter.emit_line("int main (void) {");
+        emitter.emit_line("}");
+        return emitter;
+    }
+
+    #[test]
+    fn write_to_puts_the_header_first() {
+        let mut out = Vec::new();
+        emitter().write_to(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "#include <stdio.h>\nint main (void) {\n}\n");
+    }
+
+    #[test]
+    fn missing_directories_are_created_only_when_asked() {
+        let dir = scratch_dir("create-dirs");
+        let path = dir.join("a/b/out.c");
+        let path = path.to_str().unwrap();
+
+        assert!(emitter().write_file(path, false).is_err());
+        emitter().write_file(path, true).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), emitter().output());
+    }
+
+    #[test]
+    fn unwritable_paths_are_errors() {
+        let dir = scratch_dir("unwritable");
+        fs::write(dir.join("file"), "").unwrap();
+
+        // A regular file can't be a directory, not even for root.
+        let path = dir.join("file/out.c");
+        assert!(emitter().write_file(path.to_str().unwrap(), false).is_err());
+        assert!(emitter().write_file(path.to_str().unwrap(), true).is_err());
+        assert!(emitter().write_file(dir.to_str().unwrap(), false).is_err());
+    }
+}
@@ -1,237 +1,916 @@
 use std::fmt;
+use std::rc::Rc;
 
-pub struct Lexer {
-    source: Vec<char>,
+use crate::diagnostic::ErrorCode;
+use crate::span::Span;
+use crate::trace::Tracer;
+
+/**
+ * Language the source is written in; decides which words are keywords.
+ */
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+pub enum Dialect {
+    // Teeny Tiny BASIC: PRINT, LET, IF ... THEN ... ENDIF, ... in any case.
+    Basic,
+    // Lua 5.4: lowercase, case-sensitive reserved words.
+    Lua,
+}
+
+impl Dialect {
+    /**
+     * Dialect a file is written in, going by its extension in any case.
+     */
+    pub fn from_path(path: &str) -> Option<Dialect> {
+        let extension = std::path::Path::new(path).extension()?.to_str()?.to_lowercase();
+
+        return match extension.as_str() {
+            "lua" => Some(Dialect::Lua),
+            "program" | "bas" | "teeny" => Some(Dialect::Basic),
+            _ => None,
+        };
+    }
+
+    pub fn keywords(&self) -> &'static [TokenType] {
+        return match self {
+            Dialect::Basic => &BASIC_KEYWORDS,
+            Dialect::Lua => &LUA_KEYWORDS,
+        };
+    }
+}
+
+impl TryFrom<&str> for Dialect {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        return match value {
+            "basic" => Ok(Dialect::Basic),
+            "lua" => Ok(Dialect::Lua),
+            _ => Err(format!("Unknown dialect: {value} (expected basic or lua)")),
+        };
+    }
+}
+
+const BASIC_KEYWORDS: [TokenType; 11] = [
+    TokenType::LABEL, TokenType::GOTO, TokenType::PRINT, TokenType::INPUT, TokenType::LET,
+    TokenType::IF, TokenType::THEN, TokenType::ENDIF,
+    TokenType::WHILE, TokenType::REPEAT, TokenType::ENDWHILE,
+];
+
+const LUA_KEYWORDS: [TokenType; 22] = [
+    TokenType::AND, TokenType::BREAK, TokenType::DO, TokenType::ELSE, TokenType::ELSEIF,
+    TokenType::END, TokenType::FALSE, TokenType::FOR, TokenType::FUNCTION, TokenType::GOTO,
+    TokenType::IF, TokenType::IN, TokenType::LOCAL, TokenType::NIL, TokenType::NOT,
+    TokenType::OR, TokenType::REPEAT, TokenType::RETURN, TokenType::THEN, TokenType::TRUE,
+    TokenType::UNTIL, TokenType::WHILE,
+];
+
+/**
+ * Splits a borrowed source into tokens, which point back into the source instead of copying it.
+ *
+ * The source is read as if it ended with an extra line break, so the last statement always ends with a NEWLINE.
+ */
+pub struct Lexer<'a> {
+    source: &'a str,
+    file: Rc<str>,
+    dialect: Dialect,
     pub current_char: char,
-    pub current_position: i32,
+    // Byte offset of the current char.
+    pub current_position: usize,
+
+    // Location of the current char.
+    current_line: usize,
+    current_column: usize,
+
+    tracer: Tracer,
+    finished: bool,
 }
 
-impl Lexer {
-    pub fn new(source: Vec<char>) -> Self {
+impl<'a> Lexer<'a> {
+    pub fn new(source: &'a str, file: &str) -> Self {
 
         let mut lexer = Lexer {
-            source: source,
+            source,
+            file: Rc::from(file),
+            dialect: Dialect::Basic,
             current_char: '\n',
-            current_position: -1
+            current_position: 0,
+            current_line: 1,
+            current_column: 1,
+            tracer: Tracer::disabled("lexer"),
+            finished: false,
         };
 
-        
-        lexer.source.push('\n');
-        lexer.next_char(1);
+        lexer.current_char = lexer.peek_nth(0);
+
+        // A byte order mark takes no column, it isn't text.
+        if lexer.current_char == '\u{FEFF}' {
+            lexer.current_position += lexer.current_char.len_utf8();
+            lexer.current_char = lexer.peek_nth(0);
+        }
+
+        // Like the lua binary, ignore a first line starting with '#', e.g. "#!/usr/bin/env lua". Its line break stays.
+        if lexer.current_char == '#' {
+            while lexer.current_char != '\n' {
+                lexer.next_char(1);
+            }
+        }
 
         return lexer;
     }
 
-    pub fn next_char(&mut self, steps: i32) -> () {
-        self.current_position += steps;
-        if self.current_position >= self.source.len() as i32 {
-            self.current_char = '\0';
-            return;
+    /**
+     * Choose the language whose keywords are recognized.
+     */
+    pub fn set_dialect(&mut self, dialect: Dialect) -> () {
+        self.dialect = dialect;
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        return self.dialect;
+    }
+
+    /**
+     * Trace every token as it is read.
+     */
+    pub fn set_trace(&mut self, enabled: bool) -> () {
+        self.tracer = Tracer::new("lexer", enabled);
+    }
+
+    pub fn next_char(&mut self, steps: usize) -> () {
+        for _ in 0..steps {
+            if self.at_end() {
+                break;
+            }
+
+            // The line break added at the end isn't in the source, the end stays on its last line.
+            if self.current_position < self.source.len() {
+                if self.current_char == '\n' {
+                    self.current_line += 1;
+                    self.current_column = 1;
+                } else {
+                    self.current_column += 1;
+                }
+            }
+
+            self.current_position += self.current_char.len_utf8();
+
+            self.current_char = self.peek_nth(0);
         }
+    }
 
-        self.current_char = *self.source.get(self.current_position as usize).unwrap();
+    /**
+     * Span from the given start location up to the current char, or the end of the source.
+     */
+    fn span_from(&self, line: usize, column: usize, start: usize) -> Span {
+        let len = self.source.len();
+        return Span::new(self.file.clone(), line, column, start.min(len), self.current_position.min(len));
+    }
+
+    /**
+     * Span covering just the current char.
+     */
+    fn current_char_span(&self) -> Span {
+        let end = self.current_position + self.current_char.len_utf8();
+        return Span::new(self.file.clone(), self.current_line, self.current_column, self.current_position, end);
     }
 
     pub fn peek(&self) -> char {
-        if ((self.current_position + 1) as usize) >= self.source.len() {
+        return self.peek_nth(1);
+    }
+
+    /**
+     * Char n positions after the current one, '\0' past the end.
+     */
+    fn peek_nth(&self, n: usize) -> char {
+        let Some(rest) = self.source.get(self.current_position..) else {
             return '\0';
-        }
+        };
+
+        return rest.chars().chain(std::iter::once('\n')).nth(n).unwrap_or('\0');
+    }
+
+    /**
+     * Source text of the next width chars, all ASCII, starting at the current one.
+     */
+    fn text_ahead(&self, width: usize) -> &'a str {
+        // Only the line break added at the end of the source can be missing from it.
+        return self.source.get(self.current_position..self.current_position + width).unwrap_or("\n");
+    }
 
-        return *self.source.get((self.current_position + 1) as usize).unwrap()
+    /**
+     * Source text from the start offset up to and including the current char.
+     */
+    fn text_from(&self, start: usize) -> &'a str {
+        let end = (self.current_position + self.current_char.len_utf8()).min(self.source.len());
+        return &self.source[start..end];
     }
 
-    pub fn get_token(&mut self) -> Token {
+    pub fn get_token(&mut self) -> Result<Token<'a>, LexError> {
         self.skip_whitespace();
-        self.skip_comment();
-
-        let combination = [self.current_char, self.peek()];
-        let token: Token = match combination {
-            ['+',  _ ] => Token::new(self.current_char.to_string(), TokenType::PLUS),
-            ['-',  _ ] => Token::new(self.current_char.to_string(), TokenType::MINUS),
-            ['*',  _ ] => Token::new(self.current_char.to_string(), TokenType::ASTERISK),
-            ['/',  _ ] => Token::new(self.current_char.to_string(), TokenType::SLASH),
-            ['\n', _ ] => Token::new(self.current_char.to_string(), TokenType::NEWLINE),
-
-            ['>', '='] => Token::new(String::from_iter(combination), TokenType::GTEQ),
-            ['>',  _ ] => Token::new(self.current_char.to_string(), TokenType::GT),
-
-            ['<', '='] => Token::new(String::from_iter(combination), TokenType::LTEQ),
-            ['<',  _ ] => Token::new(self.current_char.to_string(), TokenType::LT),
-
-            ['!', '='] => Token::new(String::from_iter(combination), TokenType::NOTEQ),
-            ['!',  _ ] => Token::new(self.current_char.to_string(), TokenType::NOT),
-
-            ['=', '='] => Token::new(String::from_iter(combination), TokenType::EQEQ),
-            ['=',  _ ] => Token::new(self.current_char.to_string(), TokenType::EQ),
-            
-            ['\0', _ ] => Token::new(self.current_char.to_string(), TokenType::EOF),
-            _ => self.check_unknown_char()
+        while self.skip_comment()? {
+            self.skip_whitespace();
+        }
+
+        let (line, column, start) = (self.current_line, self.current_column, self.current_position);
+
+        // Longest match first, e.g. "..." before ".." before ".".
+        let combination = [self.current_char, self.peek(), self.peek_nth(2)];
+        let basic = self.dialect == Dialect::Basic;
+        let mut scanned = false;
+        let mut token: Token = match combination {
+            _ if self.at_end() => Token::new("", TokenType::EOF),
+
+            ['+',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::PLUS),
+            ['-',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::MINUS),
+            ['*',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::ASTERISK),
+            ['%',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::PERCENT),
+            ['^',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::CARET),
+            ['#',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::HASH),
+            ['&',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::AMPERSAND),
+            ['|',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::PIPE),
+            [';',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::SEMICOLON),
+            [',',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::COMMA),
+            ['(',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::LPAREN),
+            [')',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::RPAREN),
+            // "[[" and "[=" open a long string, see check_string.
+            ['[',  c ,  _ ] if c != '[' && c != '=' => Token::new(self.text_ahead(1), TokenType::LBRACKET),
+            [']',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::RBRACKET),
+            ['{',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::LBRACE),
+            ['}',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::RBRACE),
+            ['\n', _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::NEWLINE),
+
+            ['/', '/',  _ ] => Token::new(self.text_ahead(2), TokenType::SLASHSLASH),
+            ['/',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::SLASH),
+
+            ['>', '=',  _ ] => Token::new(self.text_ahead(2), TokenType::GTEQ),
+            ['>', '>',  _ ] => Token::new(self.text_ahead(2), TokenType::GTGT),
+            ['>',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::GT),
+
+            ['<', '=',  _ ] => Token::new(self.text_ahead(2), TokenType::LTEQ),
+            ['<', '<',  _ ] => Token::new(self.text_ahead(2), TokenType::LTLT),
+            ['<',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::LT),
+
+            ['~', '=',  _ ] => Token::new(self.text_ahead(2), TokenType::NOTEQ),
+            ['~',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::TILDE),
+
+            // "!" and "!=" only exist in BASIC; Lua spells them "not" and "~=".
+            ['!', '=',  _ ] if basic => Token::new(self.text_ahead(2), TokenType::NOTEQ),
+            ['!',  _ ,  _ ] if basic => Token::new(self.text_ahead(1), TokenType::NOT),
+
+            ['=', '=',  _ ] => Token::new(self.text_ahead(2), TokenType::EQEQ),
+            ['=',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::EQ),
+
+            [':', ':',  _ ] => Token::new(self.text_ahead(2), TokenType::COLONCOLON),
+            [':',  _ ,  _ ] => Token::new(self.text_ahead(1), TokenType::COLON),
+
+            // ".5" is a number, see check_numeric.
+            ['.', '.', '.'] => Token::new(self.text_ahead(3), TokenType::DOTDOTDOT),
+            ['.', '.',  _ ] => Token::new(self.text_ahead(2), TokenType::DOTDOT),
+            ['.',  c ,  _ ] if ! c.is_ascii_digit() => Token::new(self.text_ahead(1), TokenType::DOT),
+
+            _ => {
+                scanned = true;
+                self.check_unknown_char()?
+            },
         };
 
-        // abort if Unknown 
+        // Fail if Unknown, skipping the char so the lexer can carry on afterwards.
         if token.ttype == TokenType::Unknown {
             let mut message = "Unknown token! ".to_string();
             message.push(self.current_char);
-            self.abort(message);
-        }
-
-        if [TokenType::STRING, TokenType::NUMBER].contains(&token.ttype) {
             self.next_char(1);
-            return token;
+            return Err(LexError::new(ErrorCode::UnknownToken, message, self.span_from(line, column, start)));
         }
 
-        // Jump to next char if using double token
-        if [TokenType::GTEQ, TokenType::LTEQ, TokenType::EQEQ, TokenType::NOTEQ].contains(&token.ttype) {
-            self.next_char(2);
-            return token;
+        // Jump past the whole symbol; scanned literals and names already stop on their last char.
+        if scanned {
+            self.next_char(1);
+        } else {
+            self.next_char(token.text.len());
         }
 
-        self.next_char(1);
-        return token;
+        token.span = self.span_from(line, column, start);
+        self.tracer.event("token", &token);
+        return Ok(token);
 
     }
 
+    /**
+     * Skip blanks. Line breaks end statements in BASIC, so they are only skipped in Lua.
+     */
     fn skip_whitespace(&mut self) {
-        while [' ', '\t', '\r'].contains(&self.current_char) {
+        loop {
+            let blank = match self.dialect {
+                Dialect::Basic => [' ', '\t', '\r'].contains(&self.current_char),
+                Dialect::Lua => [' ', '\t', '\r', '\n', '\x0B', '\x0C'].contains(&self.current_char),
+            };
+
+            if ! blank || self.at_end() {
+                return;
+            }
+
             self.next_char(1);
         }
     }
 
-    fn skip_comment(&mut self) -> () {
-        if self.current_char == '-' && self.peek() == '-' {
-            while self.current_char != '\n' {
-                self.next_char(1);
+    /**
+     * Skip a "--" comment, to the end of the line or, for "--[[", to the closing long bracket.
+     * Returns whether there was a comment.
+     */
+    fn skip_comment(&mut self) -> Result<bool, LexError> {
+        if self.current_char != '-' || self.peek() != '-' {
+            return Ok(false);
+        }
+
+        let (line, column, start) = (self.current_line, self.current_column, self.current_position);
+        self.next_char(2);
+
+        if let Some(level) = self.long_bracket_level() {
+            self.read_long_bracket(level, "comment", line, column, start)?;
+            self.next_char(1);
+            return Ok(true);
+        }
+
+        while self.current_char != '\n' {
+            self.next_char(1);
+        }
+
+        return Ok(true);
+    }
+
+    /**
+     * Number of '=' in the opening long bracket at the current char, e.g. 0 for "[[" and 2 for "[==[".
+     */
+    fn long_bracket_level(&self) -> Option<usize> {
+        if self.current_char != '[' {
+            return None;
+        }
+
+        let mut level = 0;
+        while self.peek_nth(level + 1) == '=' {
+            level += 1;
+        }
+
+        if self.peek_nth(level + 1) == '[' {
+            return Some(level);
+        }
+
+        return None;
+    }
+
+    /**
+     * Read a long string or comment from its opening bracket up to the closing bracket of the same level,
+     * leaving the lexer on the last ']'. A line break right after the opening bracket is not part of the contents.
+     */
+    fn read_long_bracket(&mut self, level: usize, what: &str, line: usize, column: usize, start: usize) -> Result<String, LexError> {
+        self.next_char(level + 2);
+
+        if ['\n', '\r'].contains(&self.current_char) {
+            self.skip_line_break();
+        }
+
+        let mut contents = String::new();
+        loop {
+            if self.at_end() {
+                return Err(LexError::new(ErrorCode::IllegalString, format!("Unterminated long {what}"), self.span_from(line, column, start)));
             }
+
+            if self.current_char == ']' && (1..=level).all(|n| self.peek_nth(n) == '=') && self.peek_nth(level + 1) == ']' {
+                self.next_char(level + 1);
+                return Ok(contents);
+            }
+
+            // Any line break ("\n", "\r", "\r\n" or "\n\r") is read as a single "\n".
+            if ['\n', '\r'].contains(&self.current_char) {
+                self.skip_line_break();
+                contents.push('\n');
+                continue;
+            }
+
+            contents.push(self.current_char);
+            self.next_char(1);
+        }
+    }
+
+    /**
+     * Skip a line break at the current char, "\r\n" and "\n\r" counting as one.
+     */
+    fn skip_line_break(&mut self) -> () {
+        let first = self.current_char;
+        self.next_char(1);
+        if ['\n', '\r'].contains(&self.current_char) && self.current_char != first && ! self.at_end() {
+            self.next_char(1);
         }
     }
 
-    fn check_unknown_char(&mut self) -> Token {
+    fn check_unknown_char(&mut self) -> Result<Token<'a>, LexError> {
 
         // Try to get string token
-        let string_token_option = self.check_string();
+        let string_token_option = self.check_string()?;
         if let Some(string_token) = string_token_option {
-            return string_token;
+            return Ok(string_token);
         }
 
         // Try to get numeric token
-        let numeric_token_option = self.check_numeric();
+        let numeric_token_option = self.check_numeric()?;
         if let Some(numeric_token) = numeric_token_option {
-            return numeric_token;
+            return Ok(numeric_token);
         }
 
         // Try to get ident token
         let ident_token_option = self.check_ident();
         if let Some(ident_token) = ident_token_option {
-            return ident_token;
+            return Ok(ident_token);
         }
 
         // No token found
-        return Token::new(self.current_char.to_string(), TokenType::Unknown);
+        return Ok(Token::new(self.text_from(self.current_position), TokenType::Unknown));
     }
 
-    fn check_string(&mut self) -> Option<Token> {
-        if self.current_char == '\"' {
-            self.next_char(1);
-            let start_pos = self.current_position as usize;
+    fn check_string(&mut self) -> Result<Option<Token<'a>>, LexError> {
+        if self.current_char == '[' {
+            return self.check_long_string();
+        }
+
+        let quote = self.current_char;
+        if quote != '\"' && quote != '\'' {
+            return Ok(None);
+        }
 
-            while self.current_char != '\"' {
-                if ['\r', '\n', '\t', '\\', '%'].contains(&self.current_char) {
-                    self.abort("Illegal character in string".to_string());
+        let (line, column, start) = (self.current_line, self.current_column, self.current_position);
+        self.next_char(1);
+        let start_pos = self.current_position;
+        let mut value: Vec<u8> = Vec::new();
+
+        while self.current_char != quote {
+            if self.at_end() || ['\n', '\r'].contains(&self.current_char) {
+                return Err(LexError::new(ErrorCode::IllegalString, "Unterminated string".to_string(), self.span_from(line, column, start)));
+            }
+
+            if self.current_char == '\\' {
+                if let Err(error) = self.escape(&mut value) {
+                    self.skip_rest_of_string(quote);
+                    return Err(error);
                 }
+                continue;
+            }
+
+            let mut buffer = [0; 4];
+            value.extend_from_slice(self.current_char.encode_utf8(&mut buffer).as_bytes());
+            self.next_char(1);
+        }
+
+        let text_token = &self.source[start_pos..self.current_position];
+
+        return Ok(Some(Token::new(text_token, TokenType::STRING).with_literal(Literal::String(value))));
+    }
+
+    fn check_long_string(&mut self) -> Result<Option<Token<'a>>, LexError> {
+        let (line, column, start) = (self.current_line, self.current_column, self.current_position);
 
+        let Some(level) = self.long_bracket_level() else {
+            // "[=" without a second '[' after the '='s.
+            while ['[', '='].contains(&self.current_char) {
                 self.next_char(1);
             }
+            return Err(LexError::new(ErrorCode::IllegalString, "Invalid long string delimiter".to_string(), self.span_from(line, column, start)));
+        };
+
+        let start_pos = self.current_position + level + 2;
+        let value = self.read_long_bracket(level, "string", line, column, start)?;
+
+        let end_pos = self.current_position - level - 1;
+        let text_token = &self.source[start_pos..end_pos];
+        return Ok(Some(Token::new(text_token, TokenType::STRING).with_literal(Literal::String(value.into_bytes()))));
+    }
+
+    /**
+     * Decode the escape sequence starting at the current backslash, leaving the lexer on the char after it.
+     */
+    fn escape(&mut self, value: &mut Vec<u8>) -> Result<(), LexError> {
+        let (line, column, start) = (self.current_line, self.current_column, self.current_position);
+        self.next_char(1);
+
+        let simple = match self.current_char {
+            'a' => Some(0x07),
+            'b' => Some(0x08),
+            'f' => Some(0x0C),
+            'n' => Some(b'\n'),
+            'r' => Some(b'\r'),
+            't' => Some(b'\t'),
+            'v' => Some(0x0B),
+            '\\' => Some(b'\\'),
+            '\"' => Some(b'\"'),
+            '\'' => Some(b'\''),
+            _ => None,
+        };
 
-            let end_pos = self.current_position as usize;
-            let text_token = String::from_iter(&self.source[start_pos..end_pos]);
-            return Some(Token::new(text_token, TokenType::STRING));
+        if let Some(byte) = simple {
+            value.push(byte);
+            self.next_char(1);
+            return Ok(());
         }
 
-        return None;
+        match self.current_char {
+            // A backslash before a line break keeps the line break, "\r\n" counting as one.
+            '\n' | '\r' if ! self.at_end() => {
+                self.skip_line_break();
+                value.push(b'\n');
+            },
+
+            // \z skips the following whitespace, line breaks included.
+            'z' => {
+                self.next_char(1);
+                while self.current_char.is_ascii_whitespace() && ! self.at_end() {
+                    self.next_char(1);
+                }
+            },
+
+            // \xHH, exactly two hexadecimal digits.
+            'x' => {
+                let mut byte = 0;
+                for _ in 0..2 {
+                    self.next_char(1);
+                    let Some(digit) = self.current_char.to_digit(16) else {
+                        return Err(self.escape_error("Hexadecimal digit expected", line, column, start));
+                    };
+                    byte = byte * 16 + digit;
+                }
+                value.push(byte as u8);
+                self.next_char(1);
+            },
+
+            // \u{XXX}, any value below 2^31 encoded as (extended) UTF-8.
+            'u' => {
+                self.next_char(1);
+                if self.current_char != '{' {
+                    return Err(self.escape_error("Missing '{' in \\u{xxxx}", line, column, start));
+                }
+
+                self.next_char(1);
+                let mut code: u32 = 0;
+                let mut digits = 0;
+                while let Some(digit) = self.current_char.to_digit(16) {
+                    if code > 0x7FFFFFF {
+                        return Err(self.escape_error("UTF-8 value too large", line, column, start));
+                    }
+                    code = code * 16 + digit;
+                    digits += 1;
+                    self.next_char(1);
+                }
+
+                if digits == 0 {
+                    return Err(self.escape_error("Hexadecimal digit expected", line, column, start));
+                }
+
+                if self.current_char != '}' {
+                    return Err(self.escape_error("Missing '}' in \\u{xxxx}", line, column, start));
+                }
+
+                utf8_escape(code, value);
+                self.next_char(1);
+            },
+
+            // \ddd, up to three decimal digits.
+            c if c.is_ascii_digit() => {
+                let mut byte = 0;
+                let mut digits = 0;
+                while digits < 3 && self.current_char.is_ascii_digit() {
+                    byte = byte * 10 + self.current_char.to_digit(10).unwrap();
+                    digits += 1;
+                    self.next_char(1);
+                }
+
+                if byte > 255 {
+                    return Err(LexError::new(ErrorCode::InvalidEscape, "Decimal escape too large".to_string(), self.span_from(line, column, start)));
+                }
+                value.push(byte as u8);
+            },
+
+            _ => return Err(self.escape_error("Invalid escape sequence", line, column, start)),
+        }
+
+        return Ok(());
     }
 
-    fn check_numeric(&mut self) -> Option<Token> {
-        if ! self.current_char.is_digit(10) {
-            return None;
+    /**
+     * Error for an escape sequence, spanning from its backslash up to and including the current char.
+     */
+    fn escape_error(&self, message: &str, line: usize, column: usize, start: usize) -> LexError {
+        let mut span = self.span_from(line, column, start);
+        if ! self.at_end() {
+            span.end = self.current_char_span().end;
         }
-        
-        let start_pos = self.current_position as usize;
 
-        // read while 123...
-        while self.peek().is_digit(10) {
+        return LexError::new(ErrorCode::InvalidEscape, message.to_string(), span);
+    }
+
+    /**
+     * Skip to the closing quote of a bad string, stopping early at the end of the line.
+     */
+    fn skip_rest_of_string(&mut self, quote: char) -> () {
+        while self.current_char != quote && self.current_char != '\n' && ! self.at_end() {
+            if self.current_char == '\\' {
+                self.next_char(1);
+            }
             self.next_char(1);
         }
 
-        // if 123. ...
-        if self.peek() == '.' {
-
+        if self.current_char == quote {
             self.next_char(1);
+        }
+    }
 
-            // end if 123. 1as0q83h
-            if ! self.peek().is_digit(10) {
-                self.abort("Illegal character in number.".to_string());
-            }
+    /**
+     * True once the whole source has been read.
+     */
+    fn at_end(&self) -> bool {
+        return self.current_position > self.source.len();
+    }
+
+    fn check_numeric(&mut self) -> Result<Option<Token<'a>>, LexError> {
+        let leading_dot = self.current_char == '.' && self.peek().is_ascii_digit();
+        if ! self.current_char.is_ascii_digit() && ! leading_dot {
+            return Ok(None);
+        }
+
+        let (line, column, start) = (self.current_line, self.current_column, self.current_position);
+        let start_pos = self.current_position;
 
-            // read while 123.123...
-            while self.peek().is_digit(10) {
+        let exponent = if self.current_char == '0' && ['x', 'X'].contains(&self.peek()) {
+            self.next_char(1);
+            ['p', 'P']
+        } else {
+            ['e', 'E']
+        };
+
+        // Like Lua, read everything that could belong to a numeral and only then check it: 3.4.5 and 1..2 are malformed numbers.
+        loop {
+            let next = self.peek();
+            if exponent.contains(&next) {
+                self.next_char(1);
+                if ['+', '-'].contains(&self.peek()) {
+                    self.next_char(1);
+                }
+            } else if next.is_ascii_hexdigit() || next == '.' {
                 self.next_char(1);
+            } else {
+                break;
             }
         }
 
-        let end_pos = (self.current_position + 1) as usize;
-        let token_text = String::from_iter(&self.source[start_pos..end_pos]);
+        // A numeral touching a name, e.g. 3abc, is malformed as a whole.
+        while self.peek().is_alphanumeric() || self.peek() == '_' {
+            self.next_char(1);
+        }
+
+        let token_text = self.text_from(start_pos);
+
+        let Some(literal) = Literal::number(token_text) else {
+            self.next_char(1);
+            return Err(LexError::new(ErrorCode::IllegalNumber, format!("Malformed number {token_text}"), self.span_from(line, column, start)));
+        };
 
-        return Some(Token::new(token_text, TokenType::NUMBER));
-        
+        return Ok(Some(Token::new(token_text, TokenType::NUMBER).with_literal(literal)));
     }
 
-    fn check_ident(&mut self) -> Option<Token> {
-        // Leading character is a letter, so this must be an identifier or a keyword.
-        if ! self.current_char.is_alphabetic() {
+    fn check_ident(&mut self) -> Option<Token<'a>> {
+        // Leading character is a letter or underscore, so this must be an identifier or a keyword.
+        if ! self.is_ident_char(self.current_char) || self.current_char.is_ascii_digit() {
             return None;
         }
 
-        // Get all consecutive alpha numeric characters.
-        let start_pos = self.current_position as usize;
-        while self.peek().is_alphanumeric() {
+        // Get all consecutive identifier characters.
+        let start_pos = self.current_position;
+        while self.is_ident_char(self.peek()) {
             self.next_char(1);
         }
 
         // Check if the token is in the list of keywords.
-        let end_pos = (self.current_position + 1) as usize;
-        let token_text = String::from_iter(&self.source[start_pos..end_pos]);
-        let ident_option = match TokenType::try_from(token_text.to_uppercase().as_str()) {
-            Ok(ttype) => Some(ttype),
-            Err(_) => None,
-        };
+        let token_text = self.text_from(start_pos);
 
-        // Check if the identifier found is valid
-        if let Some(ident) = ident_option {
-            let type_value = ident.clone() as i32;
-            if type_value >= 100 && type_value < 200 {
-                return Some(Token::new(token_text, ident))
-            }
+        if let Some(keyword) = self.keyword(token_text) {
+            return Some(Token::new(token_text, keyword));
         }
 
         // Return new identifier
         return Some(Token::new(token_text, TokenType::IDENT));
     }
 
-    fn abort(&self, message: String) -> () {
-        println!("Aborted! {}", message);
-        std::process::exit(1)
+    /**
+     * Letters, digits and underscores. Lua only allows ASCII ones.
+     */
+    fn is_ident_char(&self, c: char) -> bool {
+        return match self.dialect {
+            Dialect::Basic => c.is_alphanumeric() || c == '_',
+            Dialect::Lua => c.is_ascii_alphanumeric() || c == '_',
+        };
+    }
+
+    /**
+     * Keyword spelled by the text in the current dialect, if any.
+     */
+    fn keyword(&self, text: &str) -> Option<TokenType> {
+        // BASIC keywords can be written in any case, Lua keywords are always lowercase.
+        if self.dialect == Dialect::Lua && text.chars().any(|c| c.is_uppercase()) {
+            return None;
+        }
+
+        let ttype = TokenType::try_from(text.to_uppercase().as_str()).ok()?;
+        if self.dialect.keywords().contains(&ttype) {
+            return Some(ttype);
+        }
+
+        return None;
+    }
+
+}
+
+/**
+ * Every token up to and including the EOF token, then None. Errors are yielded in place of the bad token
+ * and the lexer carries on after them.
+ */
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token<'a>, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let result = self.get_token();
+        if let Ok(token) = &result {
+            self.finished = token.ttype == TokenType::EOF;
+        }
+
+        return Some(result);
+    }
+}
+
+/**
+ * Error raised when the source text can't be split into tokens.
+ */
+#[derive(Clone, Debug)]
+pub struct LexError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub span: Span,
+}
+
+impl LexError {
+    pub fn new(code: ErrorCode, message: String, span: Span) -> Self {
+        return LexError { code, message, span };
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
     }
 }
 
+impl std::error::Error for LexError {}
+
 #[derive(Clone)]
-pub struct Token {
-    pub text: String,
-    pub ttype: TokenType
+pub struct Token<'a> {
+    // Slice of the source the token was read from.
+    pub text: &'a str,
+    pub ttype: TokenType,
+    pub span: Span,
+    // Value of a literal token, so later stages don't have to read the text again.
+    pub literal: Option<Literal>,
+}
+
+impl<'a> Token<'a> {
+    /**
+     * Token with an empty span; the lexer fills in the real span once the token is complete.
+     */
+    pub fn new(text: &'a str, ttype: TokenType) -> Self {
+        return Token{text, ttype, span: Span::default(), literal: None};
+    }
+
+    pub fn with_literal(mut self, literal: Literal) -> Self {
+        self.literal = Some(literal);
+        return self;
+    }
+}
+
+/**
+ * Value of a literal token.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    Integer(i64),
+    Float(f64),
+    // Decoded contents of a string, escapes already applied. Lua strings are bytes, not necessarily UTF-8.
+    String(Vec<u8>),
+}
+
+impl Literal {
+    /**
+     * Value of a Lua numeral: decimal or hexadecimal, integer or float.
+     *
+     * As in Lua 5.4, decimal integers that don't fit in 64 bits become floats
+     * while hexadecimal integers wrap around.
+     */
+    pub fn number(text: &str) -> Option<Literal> {
+        if let Some(digits) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            if ! digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                let value = digits.chars()
+                    .fold(0u64, |value, c| value.wrapping_mul(16).wrapping_add(c.to_digit(16).unwrap() as u64));
+                return Some(Literal::Integer(value as i64));
+            }
+
+            return hex_float(digits).map(Literal::Float);
+        }
+
+        if text.chars().all(|c| c.is_ascii_digit()) {
+            if let Ok(value) = text.parse::<i64>() {
+                return Some(Literal::Integer(value));
+            }
+        }
+
+        // Only digits, '.', and an exponent can get here, so "inf" and "nan" are never accepted.
+        if text.chars().all(|c| c.is_ascii_digit() || ['.', 'e', 'E', '+', '-'].contains(&c)) {
+            return text.parse::<f64>().ok().map(Literal::Float);
+        }
+
+        return None;
+    }
+}
+
+/**
+ * Append a \u{XXX} escape as UTF-8, using Lua's 5 and 6 byte extension for values past U+10FFFF.
+ */
+fn utf8_escape(code: u32, value: &mut Vec<u8>) -> () {
+    if code < 0x80 {
+        value.push(code as u8);
+        return;
+    }
+
+    // Continuation bytes from last to first, each holding 6 bits.
+    let mut tail = Vec::new();
+    let mut code = code;
+    let mut first_max = 0x3F;
+    while code > first_max {
+        tail.push(0x80 | (code & 0x3F) as u8);
+        code >>= 6;
+        first_max >>= 1;
+    }
+
+    // The first byte starts with as many 1 bits as there are bytes in total.
+    let lead = (!first_max << 1) as u8;
+    value.push(lead | code as u8);
+    value.extend(tail.iter().rev());
+}
+
+/**
+ * Hexadecimal float after the 0x, e.g. "1p4", "A.8" or ".1p-2".
+ */
+fn hex_float(text: &str) -> Option<f64> {
+    let (mantissa, exponent) = match text.find(['p', 'P']) {
+        Some(index) => (&text[..index], Some(&text[index + 1..])),
+        None => (text, None),
+    };
+
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+
+    if ! whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut value = 0.0;
+    for c in whole.chars().chain(fraction.chars()) {
+        value = value * 16.0 + c.to_digit(16).unwrap() as f64;
+    }
+
+    // Each fraction digit is 4 bits after the point.
+    let mut power = -4 * fraction.len() as i32;
+
+    if let Some(exponent) = exponent {
+        let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        if digits.is_empty() || ! digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        power = power.saturating_add(exponent.parse::<i32>().unwrap_or(if exponent.starts_with('-') { i32::MIN } else { i32::MAX }));
+    }
+
+    return Some(value * 2f64.powi(power));
 }
 
-impl Token {
-    pub fn new(text: String, ttype: TokenType) -> Self {
-        return Token{text, ttype};
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Literal::Integer(value) => write!(f, "{value}"),
+            Literal::Float(value) => write!(f, "{value:?}"),
+            Literal::String(value) => write!(f, "\"{}\"", value.escape_ascii()),
+        }
     }
 }
 
@@ -261,6 +940,22 @@ pub enum TokenType {
 	WHILE = 109,
 	REPEAT = 110,
 	ENDWHILE = 111,
+	AND = 112,
+	BREAK = 113,
+	DO = 114,
+	ELSE = 115,
+	ELSEIF = 116,
+	END = 117,
+	FALSE = 118,
+	FOR = 119,
+	FUNCTION = 120,
+	IN = 121,
+	LOCAL = 122,
+	NIL = 123,
+	OR = 124,
+	RETURN = 125,
+	TRUE = 126,
+	UNTIL = 127,
 	
     // Operators.
 	EQ = 201,
@@ -275,6 +970,30 @@ pub enum TokenType {
 	LTEQ = 210,
 	GT = 211,
 	GTEQ = 212,
+	SLASHSLASH = 213,
+	PERCENT = 214,
+	CARET = 215,
+	HASH = 216,
+	AMPERSAND = 217,
+	PIPE = 218,
+	TILDE = 219,
+	LTLT = 220,
+	GTGT = 221,
+	DOTDOT = 222,
+
+    // Punctuation.
+	DOTDOTDOT = 301,
+	COLONCOLON = 302,
+	SEMICOLON = 303,
+	COMMA = 304,
+	DOT = 305,
+	COLON = 306,
+	LPAREN = 307,
+	RPAREN = 308,
+	LBRACKET = 309,
+	RBRACKET = 310,
+	LBRACE = 311,
+	RBRACE = 312,
 }
 
 impl fmt::Display for TokenType {
@@ -282,3 +1001,237 @@ impl fmt::Display for TokenType {
        write!(f, "{:?}", self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Dialect, Lexer, Literal, Token, TokenType};
+    use crate::diagnostic::ErrorCode;
+
+    fn tokens(source: &str) -> Vec<Token<'_>> {
+        let mut lexer = Lexer::new(source, "test.lua");
+        lexer.set_dialect(Dialect::Lua);
+        return lexer.map(|result| result.expect("should lex")).collect();
+    }
+
+    /**
+     * Types of the tokens of the source in a dialect, without the line breaks and EOF.
+     */
+    fn types(dialect: Dialect, source: &str) -> Vec<TokenType> {
+        let mut lexer = Lexer::new(source, "test");
+        lexer.set_dialect(dialect);
+        return lexer.map(|result| result.expect("should lex").ttype)
+            .filter(|ttype| *ttype != TokenType::NEWLINE && *ttype != TokenType::EOF)
+            .collect();
+    }
+
+    /**
+     * Value of the only token in the source.
+     */
+    fn literal(source: &str) -> Literal {
+        let tokens = tokens(source);
+        assert_eq!(tokens.len(), 2, "{source:?} should be a single token");
+        return tokens[0].literal.clone().expect("should be a literal");
+    }
+
+    /**
+     * Code of the first error in the source.
+     */
+    fn error(source: &str) -> ErrorCode {
+        let mut lexer = Lexer::new(source, "test.lua");
+        lexer.set_dialect(Dialect::Lua);
+        return lexer.find_map(|result| result.err()).expect("should fail to lex").code;
+    }
+
+    #[test]
+    fn hex_integers_wrap_around() {
+        assert_eq!(literal("0xff"), Literal::Integer(255));
+        assert_eq!(literal("0xffffffffffffffff"), Literal::Integer(-1));
+        assert_eq!(literal("0x10000000000000000"), Literal::Integer(0));
+    }
+
+    #[test]
+    fn decimal_integers_overflow_to_floats() {
+        assert_eq!(literal("9223372036854775807"), Literal::Integer(i64::MAX));
+        assert_eq!(literal("9223372036854775808"), Literal::Float(9223372036854775808.0));
+    }
+
+    #[test]
+    fn floats_with_exponents_and_leading_dots() {
+        assert_eq!(literal("0x1p4"), Literal::Float(16.0));
+        assert_eq!(literal("0xA.8P0"), Literal::Float(10.5));
+        assert_eq!(literal(".5"), Literal::Float(0.5));
+        assert_eq!(literal("3e2"), Literal::Float(300.0));
+        assert_eq!(literal("1E-2"), Literal::Float(0.01));
+        assert_eq!(literal("3."), Literal::Float(3.0));
+    }
+
+    #[test]
+    fn malformed_numerals() {
+        assert_eq!(error("3..4"), ErrorCode::IllegalNumber);
+        assert_eq!(error("1e"), ErrorCode::IllegalNumber);
+        assert_eq!(error("3.4.5"), ErrorCode::IllegalNumber);
+        assert_eq!(error("0x"), ErrorCode::IllegalNumber);
+        assert_eq!(error("3abc"), ErrorCode::IllegalNumber);
+    }
+
+    fn string(source: &str) -> Vec<u8> {
+        let Literal::String(bytes) = literal(source) else {
+            panic!("{source:?} should be a string");
+        };
+
+        return bytes;
+    }
+
+    #[test]
+    fn simple_escapes() {
+        assert_eq!(string(r#""\a\b\f\n\r\t\v\\\"\'""#), b"\x07\x08\x0C\n\r\t\x0B\\\"'");
+        assert_eq!(string(r#"'it\'s'"#), b"it's");
+        assert_eq!(string("\"a\\\nb\""), b"a\nb");
+    }
+
+    #[test]
+    fn decimal_escapes() {
+        assert_eq!(string(r#""\65\066\0""#), b"AB\0");
+        assert_eq!(string(r#""\2550""#), b"\xFF0");
+        assert_eq!(error(r#""\256""#), ErrorCode::InvalidEscape);
+    }
+
+    #[test]
+    fn hex_escapes() {
+        assert_eq!(string(r#""\x41\xfF""#), b"A\xFF");
+        assert_eq!(error(r#""\x4""#), ErrorCode::InvalidEscape);
+        assert_eq!(error(r#""\xg0""#), ErrorCode::InvalidEscape);
+    }
+
+    #[test]
+    fn utf8_escapes() {
+        assert_eq!(string(r#""\u{48}\u{e9}\u{20AC}""#), "Hé€".as_bytes());
+        assert_eq!(string(r#""\u{7FFFFFFF}""#), b"\xFD\xBF\xBF\xBF\xBF\xBF");
+        assert_eq!(error(r#""\u{80000000}""#), ErrorCode::InvalidEscape);
+        assert_eq!(error(r#""\u{}""#), ErrorCode::InvalidEscape);
+        assert_eq!(error(r#""\u48""#), ErrorCode::InvalidEscape);
+        assert_eq!(error(r#""\u{48""#), ErrorCode::InvalidEscape);
+    }
+
+    #[test]
+    fn z_escape_skips_whitespace() {
+        assert_eq!(string("\"a\\z  \n\t  b\""), b"ab");
+        assert_eq!(string("\"a\\zb\""), b"ab");
+    }
+
+    #[test]
+    fn invalid_escape() {
+        assert_eq!(error(r#""\q""#), ErrorCode::InvalidEscape);
+    }
+
+    #[test]
+    fn long_strings_match_their_level() {
+        assert_eq!(string("[[a]]"), b"a");
+        assert_eq!(string("[==[a]]b]=]c]==]"), b"a]]b]=]c");
+        assert_eq!(string("[=[\\n]]\"]=]"), b"\\n]]\"");
+        assert_eq!(error("[==[a]=]"), ErrorCode::IllegalString);
+        assert_eq!(error("[=a"), ErrorCode::IllegalString);
+    }
+
+    #[test]
+    fn long_strings_skip_the_first_line_break() {
+        assert_eq!(string("[[\nline]]"), b"line");
+        assert_eq!(string("[[\r\nline]]"), b"line");
+        assert_eq!(string("[[\n\nline]]"), b"\nline");
+        assert_eq!(string("[[line\n]]"), b"line\n");
+    }
+
+    #[test]
+    fn long_comments_match_their_level() {
+        let types: Vec<TokenType> = tokens("a --[==[ ]] \n ]=] ]==] b --[[\n]] c").iter().map(|token| token.ttype).collect();
+        assert_eq!(types, vec![TokenType::IDENT, TokenType::IDENT, TokenType::IDENT, TokenType::EOF]);
+
+        assert_eq!(error("--[[ never closed"), ErrorCode::IllegalString);
+    }
+
+    #[test]
+    fn errors_are_yielded_in_place_of_the_bad_token() {
+        let mut lexer = Lexer::new("x = @ y", "test.lua");
+        lexer.set_dialect(Dialect::Lua);
+        let results: Vec<Result<TokenType, ErrorCode>> = lexer.map(|result| result.map(|token| token.ttype).map_err(|error| error.code)).collect();
+
+        assert_eq!(results, vec![
+            Ok(TokenType::IDENT), Ok(TokenType::EQ), Err(ErrorCode::UnknownToken), Ok(TokenType::IDENT), Ok(TokenType::EOF),
+        ]);
+    }
+
+    #[test]
+    fn eof_is_at_the_end_of_the_source() {
+        let eof = tokens("x = ").pop().unwrap();
+        assert_eq!(eof.ttype, TokenType::EOF);
+        assert_eq!((eof.span.line, eof.span.column, eof.span.start, eof.span.end), (1, 5, 4, 4));
+
+        let eof = tokens("x = 1\n").pop().unwrap();
+        assert_eq!((eof.span.line, eof.span.column, eof.span.start, eof.span.end), (2, 1, 6, 6));
+    }
+
+    #[test]
+    fn basic_keywords_are_case_insensitive() {
+        assert_eq!(types(Dialect::Basic, "print Print PRINT"), vec![TokenType::PRINT; 3]);
+        assert_eq!(types(Dialect::Basic, "endwhile EndWhile"), vec![TokenType::ENDWHILE; 2]);
+    }
+
+    #[test]
+    fn lua_keywords_are_lowercase_only() {
+        assert_eq!(types(Dialect::Lua, "Print INPUT End While"), vec![TokenType::IDENT; 4]);
+        assert_eq!(types(Dialect::Lua, "end while"), vec![TokenType::END, TokenType::WHILE]);
+        // BASIC keywords are names in Lua whatever their case.
+        assert_eq!(types(Dialect::Lua, "print input endif"), vec![TokenType::IDENT; 3]);
+        assert_eq!(types(Dialect::Lua, "_x x_1 _"), vec![TokenType::IDENT; 3]);
+    }
+
+    #[test]
+    fn operators_take_the_longest_match() {
+        use TokenType::*;
+
+        assert_eq!(types(Dialect::Lua, ". .. ..."), vec![DOT, DOTDOT, DOTDOTDOT]);
+        assert_eq!(types(Dialect::Lua, "/ //"), vec![SLASH, SLASHSLASH]);
+        assert_eq!(types(Dialect::Lua, ": ::"), vec![COLON, COLONCOLON]);
+        assert_eq!(types(Dialect::Lua, "~ ~="), vec![TILDE, NOTEQ]);
+        assert_eq!(types(Dialect::Lua, "< << <="), vec![LT, LTLT, LTEQ]);
+        assert_eq!(types(Dialect::Lua, "> >> >="), vec![GT, GTGT, GTEQ]);
+        assert_eq!(types(Dialect::Lua, "= =="), vec![EQ, EQEQ]);
+    }
+
+    #[test]
+    fn adjacent_operators_split_by_longest_match() {
+        use TokenType::*;
+
+        assert_eq!(types(Dialect::Lua, "a..b"), vec![IDENT, DOTDOT, IDENT]);
+        assert_eq!(types(Dialect::Lua, "a...b"), vec![IDENT, DOTDOTDOT, IDENT]);
+        assert_eq!(types(Dialect::Lua, "a....b"), vec![IDENT, DOTDOTDOT, DOT, IDENT]);
+        assert_eq!(types(Dialect::Lua, "a<<=b"), vec![IDENT, LTLT, EQ, IDENT]);
+        assert_eq!(types(Dialect::Lua, "a///b"), vec![IDENT, SLASHSLASH, SLASH, IDENT]);
+        assert_eq!(types(Dialect::Lua, "a:::b"), vec![IDENT, COLONCOLON, COLON, IDENT]);
+        assert_eq!(types(Dialect::Lua, "a~==b"), vec![IDENT, NOTEQ, EQ, IDENT]);
+    }
+
+    #[test]
+    fn dialect_follows_the_extension() {
+        assert_eq!(Dialect::from_path("main.lua"), Some(Dialect::Lua));
+        assert_eq!(Dialect::from_path("dir.bas/main.lua"), Some(Dialect::Lua));
+        assert_eq!(Dialect::from_path("test.program"), Some(Dialect::Basic));
+        assert_eq!(Dialect::from_path("test.bas"), Some(Dialect::Basic));
+        assert_eq!(Dialect::from_path("test.teeny"), Some(Dialect::Basic));
+        assert_eq!(Dialect::from_path("MAIN.LUA"), Some(Dialect::Lua));
+        assert_eq!(Dialect::from_path("TEST.Bas"), Some(Dialect::Basic));
+
+        // Left to --dialect or the default.
+        assert_eq!(Dialect::from_path("notes.txt"), None);
+        assert_eq!(Dialect::from_path("main"), None);
+        assert_eq!(Dialect::from_path(".lua"), None);
+        assert_eq!(Dialect::from_path("-"), None);
+    }
+
+    #[test]
+    fn dialect_names() {
+        assert_eq!(Dialect::try_from("lua"), Ok(Dialect::Lua));
+        assert_eq!(Dialect::try_from("basic"), Ok(Dialect::Basic));
+        assert_eq!(Dialect::try_from("cobol"), Err("Unknown dialect: cobol (expected basic or lua)".to_string()));
+    }
+}
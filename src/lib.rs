@@ -0,0 +1,223 @@
+#![allow(clippy::needless_return, clippy::unused_unit, clippy::upper_case_acronyms)]
+
+pub mod span;
+pub mod diagnostic;
+pub mod lexer;
+pub mod ast;
+pub mod parser;
+pub mod analysis;
+pub mod codegen;
+pub mod emitter;
+pub mod trace;
+
+use ast::Block;
+use codegen::Generator;
+use diagnostic::{Diagnostic, Diagnostics};
+use emitter::Emitter;
+use std::io::{self, Write};
+use lexer::{Dialect, Lexer, Token};
+use parser::{ParseError, Parser, DEFAULT_MAX_ERRORS};
+
+/**
+ * Last stage to run; its result is the output of the compilation.
+ */
+#[derive(PartialEq)]
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+pub enum Emit {
+    Tokens,
+    Ast,
+    C,
+}
+
+impl Emit {
+    /**
+     * File extension for this stage's output.
+     */
+    pub fn extension(&self) -> &'static str {
+        return match self {
+            Emit::Tokens => "tokens",
+            Emit::Ast => "ast",
+            Emit::C => "c",
+        };
+    }
+}
+
+impl TryFrom<&str> for Emit {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        return match value {
+            "tokens" => Ok(Emit::Tokens),
+            "ast" => Ok(Emit::Ast),
+            "c" => Ok(Emit::C),
+            _ => Err(format!("Unknown stage to emit: {value} (expected tokens, ast or c)")),
+        };
+    }
+}
+
+/**
+ * Settings for a single compilation.
+ */
+#[derive(Clone, Debug)]
+pub struct CompileOptions {
+    // Name used for the source in spans and diagnostics.
+    pub file_name: String,
+    pub dialect: Dialect,
+    pub emit: Emit,
+    pub max_errors: usize,
+    pub trace_lexer: bool,
+    pub trace_parser: bool,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        return CompileOptions {
+            file_name: "<input>".to_string(),
+            dialect: Dialect::Basic,
+            emit: Emit::C,
+            max_errors: DEFAULT_MAX_ERRORS,
+            trace_lexer: false,
+            trace_parser: false,
+        };
+    }
+}
+
+/**
+ * Result of the last stage that was run.
+ */
+pub enum Output<'a> {
+    Tokens(Vec<Token<'a>>),
+    Ast(Block),
+    C(Emitter),
+}
+
+impl Output<'_> {
+    /**
+     * The output as text, the way it is written to the output file.
+     */
+    pub fn text(&self) -> String {
+        return match self {
+            Output::Tokens(tokens) => tokens.iter()
+                .map(|token| match &token.literal {
+                    Some(literal) => format!("{}:{}\t{}\t{:?}\t{literal}\n", token.span.line, token.span.column, token.ttype, token.text),
+                    None => format!("{}:{}\t{}\t{:?}\n", token.span.line, token.span.column, token.ttype, token.text),
+                })
+                .collect(),
+            Output::Ast(block) => ast::dump(block),
+            Output::C(emitter) => emitter.output(),
+        };
+    }
+
+    pub fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        return match self {
+            Output::C(emitter) => emitter.write_to(out),
+            _ => {
+                out.write_all(self.text().as_bytes())?;
+                out.flush()
+            },
+        };
+    }
+
+    pub fn write_file(&self, full_path: &str, create_dirs: bool) -> io::Result<()> {
+        return match self {
+            Output::C(emitter) => emitter.write_file(full_path, create_dirs),
+            _ => self.write_to(&mut emitter::create_file(full_path, create_dirs)?),
+        };
+    }
+}
+
+/**
+ * Compile a program held in memory, stopping after the stage chosen in the options.
+ */
+pub fn compile<'a>(source: &'a str, options: &CompileOptions) -> Result<Output<'a>, Diagnostics> {
+    let mut lexer = Lexer::new(source, &options.file_name);
+    lexer.set_dialect(options.dialect);
+    lexer.set_trace(options.trace_lexer);
+
+    if options.emit == Emit::Tokens {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+
+        for result in lexer {
+            match result {
+                Ok(token) => tokens.push(token),
+                Err(error) => errors.push(ParseError::from(error)),
+            }
+        }
+
+        if ! errors.is_empty() {
+            return Err(to_diagnostics(errors));
+        }
+
+        return Ok(Output::Tokens(tokens));
+    }
+
+    let mut parser = Parser::new(lexer);
+    parser.set_max_errors(options.max_errors);
+    parser.set_trace(options.trace_parser);
+
+    let block = parser.program().map_err(to_diagnostics)?;
+
+    if options.emit == Emit::Ast {
+        return Ok(Output::Ast(block));
+    }
+
+    let mut emitter = Emitter::new();
+    Generator::new(&mut emitter).program(&block);
+
+    return Ok(Output::C(emitter));
+}
+
+fn to_diagnostics(errors: Vec<ParseError>) -> Diagnostics {
+    return Diagnostics::new(errors.into_iter().map(Diagnostic::from).collect());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compile, CompileOptions, Emit, Output};
+    use crate::lexer::{Dialect, TokenType};
+
+    fn options(emit: Emit) -> CompileOptions {
+        return CompileOptions { dialect: Dialect::Lua, emit, ..CompileOptions::default() };
+    }
+
+    #[test]
+    fn compile_stops_after_the_chosen_stage() {
+        let Ok(Output::Tokens(tokens)) = compile("print(1)", &options(Emit::Tokens)) else {
+            panic!("should give tokens");
+        };
+        assert_eq!(tokens.iter().map(|token| token.ttype).collect::<Vec<_>>(), vec![
+            TokenType::IDENT, TokenType::LPAREN, TokenType::NUMBER, TokenType::RPAREN, TokenType::EOF,
+        ]);
+
+        let Ok(Output::Ast(block)) = compile("print(1)", &options(Emit::Ast)) else {
+            panic!("should give the AST");
+        };
+        assert_eq!(block.stmts.len(), 1);
+
+        let Ok(output @ Output::C(_)) = compile("print(1)", &options(Emit::C)) else {
+            panic!("should give C");
+        };
+        assert!(output.text().contains("int main (void) {"));
+    }
+
+    #[test]
+    fn basic_is_the_default_dialect() {
+        let Ok(output) = compile("PRINT 1\n", &CompileOptions::default()) else {
+            panic!("should compile as BASIC");
+        };
+        assert!(output.text().contains("lua_tofloat"));
+    }
+
+    #[test]
+    fn diagnostics_name_the_file() {
+        let options = CompileOptions { file_name: "main.lua".to_string(), ..options(Emit::C) };
+        let Err(diagnostics) = compile("x = ", &options) else {
+            panic!("should not compile");
+        };
+
+        let diagnostic = diagnostics.iter().next().unwrap();
+        assert_eq!(diagnostic.span.to_string(), "main.lua:1:5");
+    }
+}
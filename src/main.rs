@@ -1,28 +1,155 @@
-mod lexer;
-mod parser;
-mod emitter;
+#![allow(clippy::needless_return, clippy::unused_unit)]
 
-use lexer::Lexer;
-use parser::Parser;
+mod cli;
+
+use cli::{Options, Verbosity};
+use lua_compiler::diagnostic::{self, Diagnostics, ErrorFormat, Renderer};
+use lua_compiler::lexer::Dialect;
+use lua_compiler::{compile, CompileOptions};
 use std::fs;
+use std::io::{IsTerminal, Read};
 
-use crate::emitter::Emitter;
+// Exit codes, see cli::USAGE.
+const EXIT_COMPILE_ERROR: i32 = 1;
+const EXIT_USAGE_ERROR: i32 = 2;
+const EXIT_IO_ERROR: i32 = 3;
 
 fn main() -> () {
-    // let source = "+- */ >>= = !=";
-    // let source = "+- \"This is a string\" -- This is a comment!\n */";
-    // let source = "+-123 9.8654*/";
-    // let source = "IF+-123 foo*THEN/";
-    
-    let contents = fs::read_to_string("./test.program")
-        .expect("Should have been able to read the file");
-
-    let lexer = Lexer::new(contents.chars().collect());
-    let mut emitter = Emitter::new("./out/out.c");
-    let mut parser = Parser::new(lexer, &mut emitter);
-
-    parser.program();
-    println!("Parsing Complete.");
-    emitter.write_file();
-    println!("Compiling complete.");
+    let options = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {message}\n\n{}", cli::USAGE);
+            std::process::exit(EXIT_USAGE_ERROR);
+        },
+    };
+
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+
+    if options.version {
+        println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        return;
+    }
+
+    // Keep going after a failed input so every file gets reported, exiting with the first failure.
+    let mut exit_code = 0;
+    let mut reports = Vec::new();
+    for input in &options.inputs {
+        if let Err(code) = compile_file(input, &options, &mut reports) {
+            if exit_code == 0 {
+                exit_code = code;
+            }
+        }
+    }
+
+    if ! reports.is_empty() {
+        eprint!("{}", render_reports(&reports, options.error_format));
+    }
+
+    std::process::exit(exit_code);
+}
+
+/**
+ * Diagnostics of a failed input with the source they point into. JSON and SARIF are written once
+ * all inputs are compiled, so several inputs still give one log.
+ */
+struct Report {
+    contents: String,
+    diagnostics: Diagnostics,
+}
+
+/**
+ * Run one input through the stages selected on the command line, returning the exit code on failure.
+ */
+fn compile_file(input: &str, options: &Options, reports: &mut Vec<Report>) -> Result<(), i32> {
+    let file_name = if input == "-" { "<stdin>" } else { input };
+    let output_path = options.output.clone().unwrap_or_else(|| cli::default_output(input, options.emit));
+
+    let contents = read_input(input).map_err(|error| {
+        eprintln!("error: could not read {file_name}: {error}");
+        return EXIT_IO_ERROR;
+    })?;
+
+    if options.verbosity == Verbosity::Verbose {
+        eprintln!("Compiling {file_name} -> {output_path}");
+    }
+
+    let compile_options = CompileOptions {
+        file_name: file_name.to_string(),
+        dialect: options.dialect.or_else(|| Dialect::from_path(input)).unwrap_or(Dialect::Basic),
+        emit: options.emit,
+        max_errors: options.max_errors,
+        trace_lexer: options.trace_lexer,
+        trace_parser: options.trace_parser,
+    };
+
+    let output = match compile(&contents, &compile_options) {
+        Ok(output) => output,
+        Err(diagnostics) if options.error_format == ErrorFormat::Human => {
+            report_errors(&contents, &diagnostics, options);
+            return Err(EXIT_COMPILE_ERROR);
+        },
+        Err(diagnostics) => {
+            reports.push(Report { contents, diagnostics });
+            return Err(EXIT_COMPILE_ERROR);
+        },
+    };
+
+    let written = if output_path == "-" {
+        output.write_to(&mut std::io::stdout().lock())
+    } else {
+        output.write_file(&output_path, options.create_dirs)
+    };
+
+    written.map_err(|error| {
+        eprintln!("error: could not write {output_path}: {error}");
+        return EXIT_IO_ERROR;
+    })?;
+
+    if options.verbosity != Verbosity::Quiet {
+        eprintln!("Compiling complete.");
+    }
+
+    return Ok(());
+}
+
+fn read_input(input: &str) -> std::io::Result<String> {
+    if input == "-" {
+        let mut contents = String::new();
+        std::io::stdin().read_to_string(&mut contents)?;
+        return Ok(contents);
+    }
+
+    return fs::read_to_string(input);
+}
+
+fn report_errors(contents: &str, diagnostics: &Diagnostics, options: &Options) -> () {
+    let color = std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    let renderer = Renderer::new(contents, color);
+
+    eprint!("{}", renderer.render_all(&diagnostics.diagnostics, options.error_format));
+
+    if options.verbosity != Verbosity::Quiet {
+        eprintln!("Aborted due to {} error(s).", diagnostics.error_count());
+    }
+}
+
+/**
+ * The diagnostics of every failed input as one JSON stream or one SARIF log.
+ */
+fn render_reports(reports: &[Report], format: ErrorFormat) -> String {
+    let renderers: Vec<Renderer> = reports.iter().map(|report| Renderer::new(&report.contents, false)).collect();
+
+    if format == ErrorFormat::Sarif {
+        let files: Vec<(&Renderer, &[_])> = renderers.iter().zip(reports)
+            .map(|(renderer, report)| (renderer, report.diagnostics.diagnostics.as_slice()))
+            .collect();
+        return diagnostic::render_sarif_files(&files) + "\n";
+    }
+
+    return renderers.iter().zip(reports)
+        .map(|(renderer, report)| renderer.render_all(&report.diagnostics.diagnostics, format))
+        .collect();
 }
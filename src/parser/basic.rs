@@ -0,0 +1,265 @@
+use crate::ast::{BinaryOp, Expr, ExprKind, Stmt, StmtKind, UnaryOp, Variable};
+use crate::diagnostic::ErrorCode;
+use crate::lexer::{Literal, TokenType};
+
+use super::{binary, ParseError, Parser};
+
+/**
+ * Grammar of the Teeny Tiny BASIC front-end, where every statement ends with a line break.
+ */
+impl Parser<'_> {
+    /**
+     * Onew of the following statements
+     */
+    pub(super) fn statement (&mut self) -> Result<Stmt, ParseError> {
+        return self.traced("statement", |parser| {
+            let start_span = parser.get_current_token_span();
+
+            let kind = match parser.get_current_token_type() {
+                // "PRINT" (expression | string)
+                TokenType::PRINT => {
+                    parser.next_token()?;
+
+                    if parser.check_token(TokenType::STRING) {
+                        let Some(Literal::String(value)) = parser.current_token.as_ref().unwrap().literal.clone() else {
+                            unreachable!("the lexer gives every string a value");
+                        };
+                        let token_span = parser.get_current_token_span();
+                        parser.next_token()?;
+
+                        StmtKind::Print(Expr::new(ExprKind::String(value), token_span))
+                    } else {
+                        StmtKind::Print(parser.expression()?)
+                    }
+                },
+
+                // "IF" comparison "THEN" {statement} "ENDIF"
+                TokenType::IF => {
+                    parser.next_token()?;
+
+                    let condition = parser.comparison()?;
+                    parser.try_match(TokenType::THEN)?;
+                    parser.new_line()?;
+
+                    let body = parser.block(&[TokenType::ENDIF], parser.get_current_token_span())?;
+
+                    parser.try_match(TokenType::ENDIF)?;
+                    StmtKind::If(condition, body, None)
+                },
+
+                // "WHILE" comparison "REPEAT" {statement} "ENDWHILE"
+                TokenType::WHILE => {
+                    parser.next_token()?;
+
+                    let condition = parser.comparison()?;
+
+                    parser.try_match(TokenType::REPEAT)?;
+                    parser.new_line()?;
+
+                    // Zero or more statements in the loop body.
+                    let body = parser.block(&[TokenType::ENDWHILE], parser.get_current_token_span())?;
+
+                    parser.try_match(TokenType::ENDWHILE)?;
+                    StmtKind::While(condition, body)
+                },
+
+                // "LABEL" ident
+                TokenType::LABEL => {
+                    parser.next_token()?;
+
+                    let name = parser.name()?;
+                    parser.declare_label(&name, false)?;
+
+                    StmtKind::Label(name)
+                },
+
+                // "GOTO" ident
+                TokenType::GOTO => {
+                    parser.next_token()?;
+
+                    let name = parser.name()?;
+                    parser.use_label(&name);
+
+                    StmtKind::Goto(name)
+                },
+
+                // "LET" ident "=" expression
+                TokenType::LET => {
+                    parser.next_token()?;
+
+                    let name = parser.name()?;
+                    parser.declare_symbol(&name.name);
+
+                    parser.try_match(TokenType::EQ)?;
+                    let target = Expr::new(ExprKind::Variable(Variable::Global(name.name)), name.span);
+                    StmtKind::Assign(vec![target], vec![parser.expression()?])
+                },
+
+                // "INPUT" ident
+                TokenType::INPUT => {
+                    parser.next_token()?;
+
+                    let name = parser.name()?;
+                    parser.declare_symbol(&name.name);
+
+                    StmtKind::Input(name)
+                },
+
+                _ => {
+                    let cur_token_text = parser.get_current_token_text();
+                    let cur_token_type_text = parser.get_current_token_type().to_string();
+
+                    return Err(parser.error(ErrorCode::InvalidStatement, format!("Invalid statement at {cur_token_text} ({cur_token_type_text})")));
+                },
+            };
+
+            let stmt = Stmt::new(kind, start_span.to(&parser.previous_span));
+
+            // New Line
+            parser.new_line()?;
+            return Ok(stmt);
+        });
+    }
+
+    pub(super) fn new_line(&mut self) -> Result<(), ParseError> {
+        self.tracer.event("new line", self.current_token.as_ref().unwrap());
+
+        // Require at least one new line.
+        self.try_match(TokenType::NEWLINE)?;
+
+        // But we will allow extra new lines too, of course.
+        while self.check_token(TokenType::NEWLINE) {
+            self.next_token()?;
+        }
+
+        return Ok(());
+    }
+
+    // comparison ::= expression (("==" | "!=" | ">" | ">=" | "<" | "<=") expression)
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        return self.traced("comparison", |parser| {
+            let mut left = parser.expression()?;
+
+            // Must be at least one comparison operator and another expression.
+            if ! parser.is_comparison_operator() {
+                let token_text = parser.get_current_token_text();
+                return Err(parser.error(ErrorCode::UnexpectedToken, format!("Expected comparison operator at: {token_text}")));
+            }
+
+            // Can have 0 or more comparison operator and expressions.
+            while parser.is_comparison_operator() {
+                let op = match parser.get_current_token_type() {
+                    TokenType::EQEQ => BinaryOp::Eq,
+                    TokenType::NOTEQ => BinaryOp::NotEq,
+                    TokenType::LT => BinaryOp::Lt,
+                    TokenType::LTEQ => BinaryOp::LtEq,
+                    TokenType::GT => BinaryOp::Gt,
+                    _ => BinaryOp::GtEq,
+                };
+                parser.next_token()?;
+
+                let right = parser.expression()?;
+                left = binary(op, left, right);
+            }
+
+            return Ok(left);
+        });
+    }
+
+    fn is_comparison_operator(&self) -> bool {
+        return [
+            TokenType::GT, TokenType::GTEQ,
+            TokenType::LT, TokenType::LTEQ,
+            TokenType::EQEQ, TokenType::NOTEQ
+        ].contains(&self.get_current_token_type());
+    }
+
+    // expression ::= term {( "-" | "+" ) term}
+    fn expression(&mut self) -> Result<Expr, ParseError> {
+        return self.traced("expression", |parser| {
+            let mut left = parser.term()?;
+
+            // can have 0 or more +/- expressions.
+            while parser.check_token(TokenType::PLUS) || parser.check_token(TokenType::MINUS) {
+                let op = if parser.check_token(TokenType::PLUS) { BinaryOp::Add } else { BinaryOp::Sub };
+                parser.next_token()?;
+
+                let right = parser.term()?;
+                left = binary(op, left, right);
+            }
+
+            return Ok(left);
+        });
+    }
+
+    // term ::= unary {( "/" | "*" ) unary}
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        return self.traced("term", |parser| {
+            let mut left = parser.unary()?;
+
+            // can have 0 or more * or / expressions.
+            while parser.check_token(TokenType::ASTERISK) || parser.check_token(TokenType::SLASH) {
+                let op = if parser.check_token(TokenType::ASTERISK) { BinaryOp::Mul } else { BinaryOp::Div };
+                parser.next_token()?;
+
+                let right = parser.unary()?;
+                left = binary(op, left, right);
+            }
+
+            return Ok(left);
+        });
+    }
+
+    // unary ::= ["+" | "-"] primary
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        return self.traced("unary", |parser| {
+            // Optional unary +/-, a plus sign changes nothing.
+            if parser.check_token(TokenType::PLUS) {
+                parser.next_token()?;
+            } else if parser.check_token(TokenType::MINUS) {
+                let start_span = parser.get_current_token_span();
+                parser.next_token()?;
+
+                let operand = parser.primary()?;
+                let span = start_span.to(&operand.span);
+                return Ok(Expr::new(ExprKind::Unary(UnaryOp::Neg, Box::new(operand)), span));
+            }
+
+            return parser.primary();
+        });
+    }
+
+    // primary ::= number | ident
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        return self.traced("primary", |parser| {
+            let cur_token = parser.current_token.as_ref().unwrap();
+            let cur_token_text = cur_token.text.to_string();
+            let cur_token_span = cur_token.span.clone();
+
+            let kind = match cur_token.ttype {
+                TokenType::NUMBER => match cur_token.literal {
+                    Some(Literal::Float(value)) => ExprKind::Float(value),
+                    Some(Literal::Integer(value)) => ExprKind::Integer(value),
+                    _ => unreachable!("the lexer gives every number a value"),
+                },
+
+                TokenType::IDENT => {
+                    if ! parser.symbols.contains(&cur_token_text) {
+                        let error = parser.error(ErrorCode::UndeclaredVariable, format!("Referencing variable berfore assignment: {cur_token_text}"));
+                        parser.report(error)?;
+                    }
+
+                    ExprKind::Variable(Variable::Global(cur_token_text))
+                },
+
+                _ => {
+                    return Err(parser.unexpected_token());
+                }
+            };
+
+            parser.next_token()?;
+            return Ok(Expr::new(kind, cur_token_span));
+        });
+    }
+
+}
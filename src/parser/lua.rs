@@ -0,0 +1,518 @@
+use std::collections::HashMap;
+
+use crate::ast::{BinaryOp, Block, Expr, ExprKind, Field, Function, Stmt, StmtKind, UnaryOp, UNARY_PRECEDENCE};
+use crate::diagnostic::ErrorCode;
+use crate::lexer::{Literal, TokenType};
+use crate::span::Span;
+
+use super::{binary, ParseError, Parser};
+
+/**
+ * Grammar of the Lua front-end. Line breaks are plain whitespace here, statements end where the grammar says they do.
+ */
+impl Parser<'_> {
+    // stat ::= ';' | '::' Name '::' | goto Name | do block end | while exp do block end
+    //        | if exp then block {elseif exp then block} [else block] end
+    //        | function Name funcbody | local function Name funcbody | local namelist ['=' explist]
+    //        | return [explist] [';'] | functioncall | varlist '=' explist
+    // Returns None for a lone ';', which only separates statements.
+    pub(super) fn stat(&mut self) -> Result<Option<Stmt>, ParseError> {
+        return self.traced("stat", |parser| {
+            let start_span = parser.get_current_token_span();
+
+            // Labels followed only by ';' and other labels are at the end of their block.
+            if ! [TokenType::SEMICOLON, TokenType::COLONCOLON].contains(&parser.get_current_token_type()) {
+                parser.label_is_not_at_block_end()?;
+            }
+
+            let kind = match parser.get_current_token_type() {
+                TokenType::SEMICOLON => {
+                    parser.next_token()?;
+                    return Ok(None);
+                },
+
+                TokenType::COLONCOLON => {
+                    parser.next_token()?;
+                    let name = parser.name()?;
+                    parser.try_match(TokenType::COLONCOLON)?;
+
+                    parser.declare_label(&name, true)?;
+                    StmtKind::Label(name)
+                },
+
+                TokenType::GOTO => {
+                    parser.next_token()?;
+                    let name = parser.name()?;
+
+                    parser.use_label(&name);
+                    StmtKind::Goto(name)
+                },
+
+                TokenType::DO => {
+                    parser.next_token()?;
+
+                    let body = parser.block(&[TokenType::END], parser.get_current_token_span())?;
+                    parser.try_match(TokenType::END)?;
+
+                    StmtKind::Do(body)
+                },
+
+                TokenType::WHILE => {
+                    parser.next_token()?;
+                    let condition = parser.exp()?;
+                    parser.try_match(TokenType::DO)?;
+
+                    let body = parser.block(&[TokenType::END], parser.get_current_token_span())?;
+                    parser.try_match(TokenType::END)?;
+
+                    StmtKind::While(condition, body)
+                },
+
+                TokenType::IF => {
+                    parser.next_token()?;
+                    return parser.if_rest(start_span).map(Some);
+                },
+
+                TokenType::FUNCTION => {
+                    parser.next_token()?;
+                    let name = parser.name()?;
+                    let target = Expr::new(ExprKind::Variable(parser.resolve(&name)), name.span.clone());
+
+                    let function = parser.funcbody(Some(name.name))?;
+                    let span = start_span.to(&parser.previous_span);
+                    StmtKind::Assign(vec![target], vec![Expr::new(ExprKind::Function(Box::new(function)), span)])
+                },
+
+                TokenType::LOCAL if parser.peek_token_type() == TokenType::FUNCTION => {
+                    parser.next_token()?;
+                    parser.next_token()?;
+                    let name = parser.name()?;
+                    let id = parser.declare_local(&name);
+
+                    let function = parser.funcbody(Some(name.name.clone()))?;
+                    StmtKind::LocalFunction(name, id, function)
+                },
+
+                TokenType::LOCAL => {
+                    parser.next_token()?;
+                    return parser.local(start_span).map(Some);
+                },
+
+                TokenType::RETURN => {
+                    parser.next_token()?;
+
+                    let values = if parser.is_block_end() || parser.check_token(TokenType::SEMICOLON) {
+                        Vec::new()
+                    } else {
+                        parser.explist()?
+                    };
+                    if parser.check_token(TokenType::SEMICOLON) {
+                        parser.next_token()?;
+                    }
+
+                    // Nothing could run after it.
+                    if ! parser.is_block_end() {
+                        let cur_token_text = parser.get_current_token_text();
+                        return Err(parser.error(ErrorCode::UnexpectedToken, format!("Expected the end of the block after return, got {cur_token_text}")));
+                    }
+
+                    StmtKind::Return(values)
+                },
+
+                // exprstat ::= functioncall | varlist '=' explist
+                TokenType::IDENT | TokenType::LPAREN => {
+                    let target = parser.suffixedexp()?;
+
+                    if matches!(target.kind, ExprKind::Call(..)) && ! parser.check_token(TokenType::EQ) && ! parser.check_token(TokenType::COMMA) {
+                        StmtKind::Call(target)
+                    } else {
+                        // varlist ::= var {',' var}
+                        let mut targets = vec![assignable(target)?];
+                        while parser.check_token(TokenType::COMMA) {
+                            parser.next_token()?;
+                            targets.push(assignable(parser.suffixedexp()?)?);
+                        }
+
+                        parser.try_match(TokenType::EQ)?;
+                        StmtKind::Assign(targets, parser.explist()?)
+                    }
+                },
+
+                _ => {
+                    let cur_token_text = parser.get_current_token_text();
+                    let cur_token_type_text = parser.get_current_token_type().to_string();
+
+                    return Err(parser.error(ErrorCode::InvalidStatement, format!("Invalid statement at {cur_token_text} ({cur_token_type_text})")));
+                },
+            };
+
+            return Ok(Some(Stmt::new(kind, start_span.to(&parser.previous_span))));
+        });
+    }
+
+    /**
+     * The rest of an if statement after its "if" or "elseif". An elseif becomes an if nested in the else branch.
+     */
+    fn if_rest(&mut self, start_span: Span) -> Result<Stmt, ParseError> {
+        let condition = self.exp()?;
+        self.try_match(TokenType::THEN)?;
+
+        let body = self.block(&[TokenType::ELSEIF, TokenType::ELSE, TokenType::END], self.get_current_token_span())?;
+
+        let else_body = match self.get_current_token_type() {
+            TokenType::ELSEIF => {
+                let elseif_span = self.get_current_token_span();
+                self.next_token()?;
+
+                let elseif = self.if_rest(elseif_span)?;
+                let span = elseif.span.clone();
+                Some(Block::new(vec![elseif], span))
+            },
+
+            TokenType::ELSE => {
+                self.next_token()?;
+                let else_body = self.block(&[TokenType::END], self.get_current_token_span())?;
+                self.try_match(TokenType::END)?;
+                Some(else_body)
+            },
+
+            _ => {
+                self.try_match(TokenType::END)?;
+                None
+            },
+        };
+
+        return Ok(Stmt::new(StmtKind::If(condition, body, else_body), start_span.to(&self.previous_span)));
+    }
+
+    /**
+     * The rest of a local declaration after its "local". The names only come into scope after
+     * their values, so in local x = x the value is the outer x.
+     */
+    fn local(&mut self, start_span: Span) -> Result<Stmt, ParseError> {
+        // namelist ::= Name {',' Name}
+        let mut names = vec![self.name()?];
+        while self.check_token(TokenType::COMMA) {
+            self.next_token()?;
+            names.push(self.name()?);
+        }
+
+        let values = if self.check_token(TokenType::EQ) {
+            self.next_token()?;
+            self.explist()?
+        } else {
+            Vec::new()
+        };
+
+        let locals = names.into_iter()
+            .map(|name| {
+                let id = self.declare_local(&name);
+                (name, id)
+            })
+            .collect();
+
+        return Ok(Stmt::new(StmtKind::Local(locals, values), start_span.to(&self.previous_span)));
+    }
+
+    /**
+     * Whether the current token ends a block, which a return statement must be last in.
+     */
+    fn is_block_end(&self) -> bool {
+        return [
+            TokenType::END, TokenType::ELSE, TokenType::ELSEIF, TokenType::UNTIL, TokenType::EOF,
+        ].contains(&self.get_current_token_type());
+    }
+
+    // funcbody ::= '(' [Name {',' Name}] ')' block end
+    // The parameters are locals of the body, and labels only reach gotos of the same function.
+    fn funcbody(&mut self, name: Option<String>) -> Result<Function, ParseError> {
+        self.try_match(TokenType::LPAREN)?;
+
+        let outer_scopes = self.scopes.len();
+        self.scopes.push(HashMap::new());
+        let label_scopes = std::mem::take(&mut self.label_scopes);
+
+        let result = self.traced("funcbody", |parser| {
+            let mut params = Vec::new();
+            if parser.check_token(TokenType::IDENT) {
+                loop {
+                    let param = parser.name()?;
+                    let id = parser.declare_local(&param);
+                    params.push((param, id));
+
+                    if ! parser.check_token(TokenType::COMMA) {
+                        break;
+                    }
+                    parser.next_token()?;
+                }
+            }
+            parser.try_match(TokenType::RPAREN)?;
+
+            let body = parser.block(&[TokenType::END], parser.get_current_token_span())?;
+            parser.try_match(TokenType::END)?;
+
+            return Ok(Function { name, params, body });
+        });
+
+        // Restored even after an error, the parse goes on after the function.
+        self.label_scopes = label_scopes;
+        self.scopes.truncate(outer_scopes);
+
+        return result;
+    }
+
+    // explist ::= exp {',' exp}
+    fn explist(&mut self) -> Result<Vec<Expr>, ParseError> {
+        let mut values = vec![self.exp()?];
+        while self.check_token(TokenType::COMMA) {
+            self.next_token()?;
+            values.push(self.exp()?);
+        }
+
+        return Ok(values);
+    }
+
+    // exp ::= subexp(0)
+    pub(super) fn exp(&mut self) -> Result<Expr, ParseError> {
+        return self.subexp(0);
+    }
+
+    // subexp(limit) ::= (simpleexp | unop subexp(UNARY_PRECEDENCE)) {binop subexp(precedence of binop)}
+    // Precedence climbing: only binary operators binding tighter than the limit are taken, so
+    // a + b * c - d groups as (a + (b * c)) - d. A right associative operator lowers the limit
+    // of its right side by one so the same operator is taken again there.
+    fn subexp(&mut self, limit: u8) -> Result<Expr, ParseError> {
+        return self.traced("subexp", |parser| {
+            let mut left = match unary_operator(parser.get_current_token_type()) {
+                Some(op) => {
+                    let start_span = parser.get_current_token_span();
+                    parser.next_token()?;
+
+                    let operand = parser.subexp(UNARY_PRECEDENCE)?;
+                    let span = start_span.to(&operand.span);
+                    Expr::new(ExprKind::Unary(op, Box::new(operand)), span)
+                },
+                None => parser.simpleexp()?,
+            };
+
+            while let Some(op) = binary_operator(parser.get_current_token_type()) {
+                let precedence = op.precedence();
+                if precedence <= limit {
+                    break;
+                }
+                parser.next_token()?;
+
+                let right_limit = if op.is_right_associative() { precedence - 1 } else { precedence };
+                let right = parser.subexp(right_limit)?;
+                left = binary(op, left, right);
+            }
+
+            return Ok(left);
+        });
+    }
+
+    // simpleexp ::= nil | false | true | Numeral | LiteralString | function funcbody | tableconstructor | suffixedexp
+    fn simpleexp(&mut self) -> Result<Expr, ParseError> {
+        return self.traced("simpleexp", |parser| {
+            let cur_token = parser.current_token.as_ref().unwrap();
+            let cur_token_span = cur_token.span.clone();
+
+            let kind = match (cur_token.ttype, cur_token.literal.clone()) {
+                (TokenType::NIL, _) => ExprKind::Nil,
+                (TokenType::FALSE, _) => ExprKind::Boolean(false),
+                (TokenType::TRUE, _) => ExprKind::Boolean(true),
+                (TokenType::NUMBER, Some(Literal::Integer(value))) => ExprKind::Integer(value),
+                (TokenType::NUMBER, Some(Literal::Float(value))) => ExprKind::Float(value),
+                (TokenType::STRING, Some(Literal::String(value))) => ExprKind::String(value),
+
+
+                (TokenType::FUNCTION, _) => {
+                    parser.next_token()?;
+                    let function = parser.funcbody(None)?;
+
+                    return Ok(Expr::new(ExprKind::Function(Box::new(function)), cur_token_span.to(&parser.previous_span)));
+                },
+
+                (TokenType::LBRACE, _) => return parser.tableconstructor(),
+
+                _ => return parser.suffixedexp(),
+            };
+
+            parser.next_token()?;
+            return Ok(Expr::new(kind, cur_token_span));
+        });
+    }
+
+    // suffixedexp ::= primaryexp {'.' Name | '[' exp ']' | args}
+    // args ::= '(' [explist] ')' | tableconstructor | LiteralString
+    fn suffixedexp(&mut self) -> Result<Expr, ParseError> {
+        return self.traced("suffixedexp", |parser| {
+            let mut expr = parser.primaryexp()?;
+
+            loop {
+                let args = match parser.get_current_token_type() {
+                    TokenType::DOT => {
+                        parser.next_token()?;
+                        let name = parser.name()?;
+                        let key = Expr::new(ExprKind::String(name.name.into_bytes()), name.span);
+
+                        let span = expr.span.to(&parser.previous_span);
+                        expr = Expr::new(ExprKind::Index(Box::new(expr), Box::new(key)), span);
+                        continue;
+                    },
+
+                    TokenType::LBRACKET => {
+                        parser.next_token()?;
+                        let key = parser.exp()?;
+                        parser.try_match(TokenType::RBRACKET)?;
+
+                        let span = expr.span.to(&parser.previous_span);
+                        expr = Expr::new(ExprKind::Index(Box::new(expr), Box::new(key)), span);
+                        continue;
+                    },
+
+                    TokenType::LPAREN => {
+                        parser.next_token()?;
+                        let args = if parser.check_token(TokenType::RPAREN) { Vec::new() } else { parser.explist()? };
+                        parser.try_match(TokenType::RPAREN)?;
+                        args
+                    },
+
+                    // f{...} and f"text" pass the table or string alone.
+                    TokenType::LBRACE => vec![parser.tableconstructor()?],
+
+                    TokenType::STRING => {
+                        let Some(Literal::String(value)) = parser.current_token.as_ref().unwrap().literal.clone() else {
+                            unreachable!("the lexer gives every string a value");
+                        };
+                        let arg = Expr::new(ExprKind::String(value), parser.get_current_token_span());
+                        parser.next_token()?;
+                        vec![arg]
+                    },
+
+                    _ => return Ok(expr),
+                };
+
+                let span = expr.span.to(&parser.previous_span);
+                expr = Expr::new(ExprKind::Call(Box::new(expr), args), span);
+            }
+        });
+    }
+
+    // tableconstructor ::= '{' [field {fieldsep field} [fieldsep]] '}'
+    // field ::= '[' exp ']' '=' exp | Name '=' exp | exp
+    // fieldsep ::= ',' | ';'
+    fn tableconstructor(&mut self) -> Result<Expr, ParseError> {
+        return self.traced("tableconstructor", |parser| {
+            let start_span = parser.get_current_token_span();
+            parser.try_match(TokenType::LBRACE)?;
+
+            let mut fields = Vec::new();
+            while ! parser.check_token(TokenType::RBRACE) {
+                let field = match parser.get_current_token_type() {
+                    TokenType::LBRACKET => {
+                        parser.next_token()?;
+                        let key = parser.exp()?;
+                        parser.try_match(TokenType::RBRACKET)?;
+                        parser.try_match(TokenType::EQ)?;
+
+                        Field::Keyed(key, parser.exp()?)
+                    },
+
+                    TokenType::IDENT if parser.peek_token_type() == TokenType::EQ => {
+                        let name = parser.name()?;
+                        parser.next_token()?;
+
+                        Field::Keyed(Expr::new(ExprKind::String(name.name.into_bytes()), name.span), parser.exp()?)
+                    },
+
+                    _ => Field::Positional(parser.exp()?),
+                };
+                fields.push(field);
+
+                if parser.check_token(TokenType::COMMA) || parser.check_token(TokenType::SEMICOLON) {
+                    parser.next_token()?;
+                } else {
+                    break;
+                }
+            }
+            parser.try_match(TokenType::RBRACE)?;
+
+            return Ok(Expr::new(ExprKind::Table(fields), start_span.to(&parser.previous_span)));
+        });
+    }
+
+    // primaryexp ::= Name | '(' exp ')'
+    fn primaryexp(&mut self) -> Result<Expr, ParseError> {
+        return self.traced("primaryexp", |parser| {
+            let start_span = parser.get_current_token_span();
+
+            match parser.get_current_token_type() {
+                // Names that aren't locals are globals, which are nil until set.
+                TokenType::IDENT => {
+                    let name = parser.name()?;
+                    let variable = parser.resolve(&name);
+                    return Ok(Expr::new(ExprKind::Variable(variable), name.span));
+                },
+
+                TokenType::LPAREN => {
+                    parser.next_token()?;
+                    let inner = parser.exp()?;
+                    parser.try_match(TokenType::RPAREN)?;
+
+                    let span = start_span.to(&parser.previous_span);
+                    return Ok(Expr::new(ExprKind::Paren(Box::new(inner)), span));
+                },
+
+                _ => {
+                    return Err(parser.unexpected_token());
+                },
+            }
+        });
+    }
+}
+
+// Only a variable or a field can be assigned.
+fn assignable(target: Expr) -> Result<Expr, ParseError> {
+    return match target.kind {
+        ExprKind::Variable(_) | ExprKind::Index(..) => Ok(target),
+        _ => Err(ParseError::new(ErrorCode::InvalidStatement, "Only a call can be a statement, or an assignment to a variable or field".to_string(), target.span)),
+    };
+}
+
+fn unary_operator(ttype: TokenType) -> Option<UnaryOp> {
+    return match ttype {
+        TokenType::NOT => Some(UnaryOp::Not),
+        TokenType::HASH => Some(UnaryOp::Len),
+        TokenType::MINUS => Some(UnaryOp::Neg),
+        TokenType::TILDE => Some(UnaryOp::BNot),
+        _ => None,
+    };
+}
+
+fn binary_operator(ttype: TokenType) -> Option<BinaryOp> {
+    return match ttype {
+        TokenType::OR => Some(BinaryOp::Or),
+        TokenType::AND => Some(BinaryOp::And),
+        TokenType::EQEQ => Some(BinaryOp::Eq),
+        TokenType::NOTEQ => Some(BinaryOp::NotEq),
+        TokenType::LT => Some(BinaryOp::Lt),
+        TokenType::LTEQ => Some(BinaryOp::LtEq),
+        TokenType::GT => Some(BinaryOp::Gt),
+        TokenType::GTEQ => Some(BinaryOp::GtEq),
+        TokenType::PIPE => Some(BinaryOp::BOr),
+        TokenType::TILDE => Some(BinaryOp::BXor),
+        TokenType::AMPERSAND => Some(BinaryOp::BAnd),
+        TokenType::LTLT => Some(BinaryOp::Shl),
+        TokenType::GTGT => Some(BinaryOp::Shr),
+        TokenType::DOTDOT => Some(BinaryOp::Concat),
+        TokenType::PLUS => Some(BinaryOp::Add),
+        TokenType::MINUS => Some(BinaryOp::Sub),
+        TokenType::ASTERISK => Some(BinaryOp::Mul),
+        TokenType::SLASH => Some(BinaryOp::Div),
+        TokenType::SLASHSLASH => Some(BinaryOp::IDiv),
+        TokenType::PERCENT => Some(BinaryOp::Mod),
+        TokenType::CARET => Some(BinaryOp::Pow),
+        _ => None,
+    };
+}
@@ -0,0 +1,753 @@
+mod basic;
+mod lua;
+
+use std::collections::{HashMap, HashSet};
+
+use crate::lexer;
+use crate::ast::{BinaryOp, Block, Expr, ExprKind, LocalId, Name, Stmt, Variable};
+use crate::diagnostic::{Diagnostic, ErrorCode, Label};
+use crate::span::Span;
+use crate::trace::Tracer;
+
+use lexer::{Dialect, Lexer, LexError, Token, TokenType};
+use std::fmt;
+
+/**
+ * Default number of errors reported before the parser gives up.
+ */
+pub const DEFAULT_MAX_ERRORS: usize = 20;
+
+/**
+ * Tokens the Lua parser can resume from after a syntax error: the ones starting or ending a statement.
+ */
+const LUA_SYNC_TOKENS: [TokenType; 16] = [
+    TokenType::LOCAL, TokenType::FUNCTION, TokenType::IF, TokenType::WHILE, TokenType::FOR,
+    TokenType::REPEAT, TokenType::DO, TokenType::RETURN, TokenType::BREAK, TokenType::GOTO, TokenType::COLONCOLON,
+    TokenType::END, TokenType::ELSE, TokenType::ELSEIF, TokenType::UNTIL, TokenType::EOF,
+];
+
+/**
+ * Labels declared in a block, and the gotos in it still waiting for a label further on.
+ */
+#[derive(Default)]
+struct LabelScope {
+    labels: HashMap<String, Span>,
+    // With the number of locals of the function in scope at each goto.
+    gotos: Vec<(Name, usize)>,
+    // Locals declared in the block so far, shadowed ones included.
+    locals: Vec<Name>,
+    // Gotos jumping into the scope of a local only if their label doesn't end the block.
+    jumps_into_scope: Vec<ParseError>,
+}
+
+/**
+ * Parser object keeps track of current token, checks if the code matches the grammar and builds the AST.
+ */
+pub struct Parser<'a> {
+    lexer: Lexer<'a>,
+    dialect: Dialect,
+    current_token: Option::<Token<'a>>,
+    peek_token: Option::<Token<'a>>,
+    previous_span: Span,
+
+    symbols: HashSet<String>,
+    // Locals of each enclosing block, innermost last.
+    scopes: Vec<HashMap<String, LocalId>>,
+    local_count: LocalId,
+    // Labels of each enclosing block of the function, innermost last.
+    label_scopes: Vec<LabelScope>,
+
+    tracer: Tracer,
+    errors: Vec<ParseError>,
+    last_lex_error: Option<usize>,
+    max_errors: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new (lexer: Lexer<'a>) -> Self {
+        return Parser {
+            dialect: lexer.dialect(),
+            lexer,
+            current_token: None,
+            peek_token: None,
+            previous_span: Span::default(),
+            symbols: HashSet::new(),
+            scopes: Vec::new(),
+            local_count: 0,
+            label_scopes: Vec::new(),
+            tracer: Tracer::disabled("parser"),
+            errors: Vec::new(),
+            last_lex_error: None,
+            max_errors: DEFAULT_MAX_ERRORS,
+        };
+    }
+
+    /**
+     * Set how many errors are reported before the parser gives up.
+     */
+    pub fn set_max_errors(&mut self, max_errors: usize) -> () {
+        self.max_errors = max_errors.max(1);
+    }
+
+    /**
+     * Trace each grammar rule as it is parsed.
+     */
+    pub fn set_trace(&mut self, enabled: bool) -> () {
+        self.tracer = Tracer::new("parser", enabled);
+    }
+
+    /**
+     * Run a grammar rule, tracing it one level deeper than the rule that called it.
+     */
+    fn traced<T>(&mut self, rule: &str, parse: impl FnOnce(&mut Self) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        let entered = match self.current_token.as_ref() {
+            Some(token) => { self.tracer.enter(rule, token); true },
+            None => false,
+        };
+
+        let result = parse(self);
+        if entered {
+            self.tracer.exit();
+        }
+
+        return result;
+    }
+
+    /**
+     * Return true if the current token matches.
+     */
+    fn check_token(&self, token_type: TokenType) -> bool {
+        return self.current_token.as_ref().unwrap().ttype == token_type;
+    }
+
+    /**
+     * Try to match current token. If not, error. Advances the current token.
+     */
+    fn try_match(&mut self, token_type: TokenType) -> Result<(), ParseError> {
+        if ! (self.check_token(token_type)) {
+            let cur_token = self.get_current_token_type().to_string();
+            let expected_token = token_type.to_string();
+            return Err(self.error(ErrorCode::UnexpectedToken, format!("Expected {expected_token}, got {cur_token}")));
+        }
+
+        return self.next_token();
+    }
+
+    /**
+     * Advances the current token.
+     */
+    fn next_token(&mut self) -> Result<(), ParseError> {
+        if let Some(token) = self.current_token.as_ref() {
+            self.previous_span = token.span.clone();
+        }
+        self.current_token = self.peek_token.clone();
+
+        // The lexer skips past whatever it couldn't read, so keep going with the next token.
+        loop {
+            match self.lexer.get_token() {
+                Ok(token) => {
+                    self.peek_token = Some(token);
+                    return Ok(());
+                },
+                Err(error) => {
+                    self.last_lex_error = Some(error.span.start);
+                    self.report(error.into())?;
+                },
+            }
+        }
+    }
+
+    /**
+     * Get the text of the current token
+     */
+    fn get_current_token_text(&self) -> String {
+        return self.current_token.as_ref().unwrap().text.to_string();
+    }
+
+    /**
+     * Get the type of the current token
+     */
+    fn get_current_token_type(&self) -> TokenType {
+        return self.current_token.as_ref().unwrap().ttype;
+    }
+
+    /**
+     * Get the type of the token after the current one
+     */
+    fn peek_token_type(&self) -> TokenType {
+        return self.peek_token.as_ref().unwrap().ttype;
+    }
+
+    /**
+     * Get the source location of the current token
+     */
+    fn get_current_token_span(&self) -> Span {
+        return self.current_token.as_ref().unwrap().span.clone();
+    }
+
+    /**
+     * Error at the location of the current token.
+     */
+    fn error(&self, code: ErrorCode, message: String) -> ParseError {
+        return ParseError::new(code, message, self.get_current_token_span());
+    }
+
+    /**
+     * Error for a token no rule can start with, naming the end of a line or of the input.
+     */
+    fn unexpected_token(&self) -> ParseError {
+        let message = match self.get_current_token_type() {
+            TokenType::EOF => "Unexpected end of input".to_string(),
+            TokenType::NEWLINE => "Unexpected end of line".to_string(),
+            _ => format!("Unexpected token at {}", self.get_current_token_text()),
+        };
+
+        return self.error(ErrorCode::UnexpectedToken, message);
+    }
+
+    /**
+     * Record an error and keep parsing. Fails once the error limit is reached so the parse unwinds.
+     */
+    fn report(&mut self, error: ParseError) -> Result<(), ParseError> {
+        // Already gave up, keep unwinding.
+        if error.code == ErrorCode::TooManyErrors {
+            return Err(error);
+        }
+
+        self.errors.push(error);
+
+        if self.errors.len() >= self.max_errors {
+            let max_errors = self.max_errors;
+            let span = self.errors[max_errors - 1].span.clone();
+            return Err(ParseError::new(ErrorCode::TooManyErrors, format!("Too many errors ({max_errors}), giving up"), span));
+        }
+
+        return Ok(());
+    }
+
+    /**
+     * Skip tokens until the start of the next statement or the end of the enclosing block.
+     */
+    fn synchronize(&mut self) -> Result<(), ParseError> {
+        // BASIC statements are lines, a keyword further on in the same line doesn't start one.
+        if self.dialect == Dialect::Basic {
+            while ! self.check_token(TokenType::NEWLINE) && ! self.check_token(TokenType::EOF) {
+                self.next_token()?;
+            }
+
+            if self.check_token(TokenType::NEWLINE) {
+                return self.new_line();
+            }
+            return Ok(());
+        }
+
+        while ! LUA_SYNC_TOKENS.contains(&self.get_current_token_type()) {
+            if self.check_token(TokenType::NEWLINE) {
+                return self.new_line();
+            }
+
+            if self.check_token(TokenType::SEMICOLON) {
+                return self.next_token();
+            }
+
+            self.next_token()?;
+        }
+
+        return Ok(());
+    }
+
+    /**
+     * Parse a statement, reporting any syntax error and resuming at the next statement.
+     */
+    fn statement_or_recover(&mut self) -> Result<Option<Stmt>, ParseError> {
+        let start = self.get_current_token_span().start;
+
+        let result = match self.dialect {
+            Dialect::Basic => self.statement().map(Some),
+            Dialect::Lua => self.stat(),
+        };
+
+        let error = match result {
+            Ok(stmt) => return Ok(stmt),
+            Err(error) => error,
+        };
+
+        // A syntax error in a statement with a lexical error is usually caused by it, so don't repeat it.
+        if error.code == ErrorCode::TooManyErrors || self.last_lex_error.is_none_or(|lex_error| lex_error < start) {
+            self.report(error)?;
+        }
+
+        self.synchronize()?;
+
+        // Always make progress, even if the error was at a token we can resume from.
+        if self.get_current_token_span().start == start && ! self.check_token(TokenType::EOF) {
+            self.next_token()?;
+            self.synchronize()?;
+        }
+
+        return Ok(None);
+    }
+
+    /**
+     * Parse the whole program, returning every error found.
+     */
+    pub fn program(&mut self) -> Result<Block, Vec<ParseError>> {
+        let result = self.parse_program();
+
+        if let Err(error) = &result {
+            if error.code == ErrorCode::TooManyErrors {
+                self.errors.push(error.clone());
+            }
+        }
+
+        if let (Ok(block), true) = (result, self.errors.is_empty()) {
+            return Ok(block);
+        }
+
+        return Err(std::mem::take(&mut self.errors));
+    }
+
+    fn parse_program(&mut self) -> Result<Block, ParseError> {
+        // Calling twice to initialize current and peek tokens.
+        self.next_token()?;
+        self.next_token()?;
+        self.tracer.event("program", self.current_token.as_ref().unwrap());
+
+        let start_span = self.get_current_token_span();
+
+        while self.check_token(TokenType::NEWLINE) {
+            self.next_token()?;
+        }
+
+        // Parse all the statements in the program
+        let block = self.block(&[TokenType::EOF], start_span)?;
+
+        return Ok(block);
+    }
+
+    /**
+     * Hand the gotos of a block that just ended to the enclosing one, whose later labels they can
+     * still reach. At the end of the function, report the ones that found no label.
+     */
+    fn close_label_scope(&mut self, scope: LabelScope) -> Result<(), ParseError> {
+        // Out of the block, its locals are no longer in scope at the gotos.
+        let outer_locals = self.active_locals().count();
+        if let Some(outer) = self.label_scopes.last_mut() {
+            outer.gotos.extend(scope.gotos.into_iter().map(|(goto, locals)| (goto, locals.min(outer_locals))));
+            return Ok(());
+        }
+
+        // BASIC labels are all kept in the outermost block, wherever they are.
+        let mut undeclared_labels: Vec<Name> = scope.gotos.into_iter()
+            .map(|(goto, _)| goto)
+            .filter(|goto| ! scope.labels.contains_key(&goto.name))
+            .collect();
+        undeclared_labels.sort_by_key(|goto| goto.span.start);
+
+        for goto in undeclared_labels {
+            self.report(ParseError::new(ErrorCode::UndeclaredLabel, format!("Attempting to GOTO to undeclared label: {}", goto.name), goto.span))?;
+        }
+
+        return Ok(());
+    }
+
+    /**
+     * Statements up to (not including) one of the tokens that end the block.
+     */
+    fn block(&mut self, end: &[TokenType], start_span: Span) -> Result<Block, ParseError> {
+        let mut stmts = Vec::new();
+
+        // Locals and labels declared in the block go out of scope at its end.
+        self.scopes.push(HashMap::new());
+        self.label_scopes.push(LabelScope::default());
+
+        while ! end.contains(&self.get_current_token_type()) && ! self.check_token(TokenType::EOF) {
+            if let Some(stmt) = self.statement_or_recover()? {
+                stmts.push(stmt);
+            }
+        }
+
+        self.scopes.pop();
+        let label_scope = self.label_scopes.pop().unwrap();
+        self.close_label_scope(label_scope)?;
+
+        let span = start_span.to(&self.get_current_token_span());
+        return Ok(Block::new(stmts, span));
+    }
+
+    /**
+     * Match an identifier, returning it.
+     */
+    fn name(&mut self) -> Result<Name, ParseError> {
+        let name = Name::new(self.get_current_token_text(), self.get_current_token_span());
+        self.try_match(TokenType::IDENT)?;
+        return Ok(name);
+    }
+
+    /**
+     * The label with this name in the current block or an enclosing one of the same function.
+     */
+    fn visible_label(&self, name: &str) -> Option<&Span> {
+        return self.label_scopes.iter().rev().find_map(|scope| scope.labels.get(name));
+    }
+
+    /**
+     * Locals of the function in scope, in the order they were declared.
+     */
+    fn active_locals(&self) -> impl Iterator<Item = &Name> {
+        return self.label_scopes.iter().flat_map(|scope| scope.locals.iter());
+    }
+
+    /**
+     * Record a label, reporting it if a label with the same name is visible. Lua labels belong to
+     * their block, BASIC ones to the whole program. A label ending its block is out of the scope
+     * of the block's locals, gotos can jump to it past them. Whether it ends its block is only known
+     * once a statement other than ';' or a label follows it, see `label_is_not_at_block_end`.
+     */
+    fn declare_label(&mut self, name: &Name, can_end_block: bool) -> Result<(), ParseError> {
+        if let Some(declared_span) = self.visible_label(&name.name) {
+            let error = ParseError::new(ErrorCode::DuplicateLabel, format!("Label already exists: {}", name.name), name.span.clone())
+                .with_label(declared_span.clone(), "label first declared here".to_string());
+            return self.report(error);
+        }
+
+        let scope = match self.dialect {
+            Dialect::Basic => self.label_scopes.first_mut(),
+            Dialect::Lua => self.label_scopes.last_mut(),
+        };
+        scope.unwrap().labels.insert(name.name.clone(), name.span.clone());
+
+        let label_locals = self.active_locals().count();
+        let outer_locals = label_locals - self.label_scopes.last().unwrap().locals.len();
+
+        // Gotos waiting in this block, or moved out of the blocks nested in it, jump here.
+        let scope = self.label_scopes.last_mut().unwrap();
+        let (gotos, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut scope.gotos).into_iter()
+            .partition(|(goto, _)| goto.name == name.name);
+        scope.gotos = waiting;
+
+        for (goto, goto_locals) in gotos {
+            if goto_locals < label_locals {
+                let local = self.active_locals().nth(goto_locals).unwrap().clone();
+                let error = ParseError::new(ErrorCode::JumpIntoScope, format!("GOTO {} jumps into the scope of local {}", goto.name, local.name), goto.span)
+                    .with_label(local.span, "local declared here".to_string());
+
+                if can_end_block && goto_locals >= outer_locals {
+                    self.label_scopes.last_mut().unwrap().jumps_into_scope.push(error);
+                } else {
+                    self.report(error)?;
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    /**
+     * A statement follows the labels declared so far in the block, so they don't end it and the gotos
+     * jumping to them past the block's locals are errors.
+     */
+    fn label_is_not_at_block_end(&mut self) -> Result<(), ParseError> {
+        let errors = std::mem::take(&mut self.label_scopes.last_mut().unwrap().jumps_into_scope);
+        for error in errors {
+            self.report(error)?;
+        }
+
+        return Ok(());
+    }
+
+    /**
+     * Record a goto. A label declared further on is looked for as the blocks end.
+     */
+    fn use_label(&mut self, name: &Name) -> () {
+        if self.visible_label(&name.name).is_none() {
+            let locals = self.active_locals().count();
+            self.label_scopes.last_mut().unwrap().gotos.push((name.clone(), locals));
+        }
+    }
+
+    /**
+     * Bring a new local into the current block's scope, hiding any variable with the same name.
+     */
+    fn declare_local(&mut self, name: &Name) -> LocalId {
+        let id = self.local_count;
+        self.local_count += 1;
+
+        self.scopes.last_mut().unwrap().insert(name.name.clone(), id);
+        // Parameters are in scope in the whole function, gotos never jump past them.
+        if let Some(scope) = self.label_scopes.last_mut() {
+            scope.locals.push(name.clone());
+        }
+        return id;
+    }
+
+    /**
+     * The innermost local with this name, or the global if there is none.
+     */
+    fn resolve(&self, name: &Name) -> Variable {
+        // A local of an enclosing function is found the same way, the closure shares it.
+        for scope in self.scopes.iter().rev() {
+            if let Some(&id) = scope.get(&name.name) {
+                return Variable::Local(name.name.clone(), id);
+            }
+        }
+
+        return Variable::Global(name.name.clone());
+    }
+
+    /**
+     * Check if ident exists in symbol table. If not, declare it.
+     */         
+    fn declare_symbol(&mut self, token_text: &str) {
+        if ! self.symbols.contains(token_text) {
+            self.symbols.insert(token_text.to_string());
+        }
+    }
+}
+
+fn binary(op: BinaryOp, left: Expr, right: Expr) -> Expr {
+    let span = left.span.to(&right.span);
+    return Expr::new(ExprKind::Binary(op, Box::new(left), Box::new(right)), span);
+}
+
+/**
+ * Error raised when the tokens don't match the grammar.
+ */
+#[derive(Clone, Debug)]
+pub struct ParseError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub span: Span,
+    pub labels: Vec<Label>,
+}
+
+impl ParseError {
+    pub fn new(code: ErrorCode, message: String, span: Span) -> Self {
+        return ParseError { code, message, span, labels: Vec::new() };
+    }
+
+    /**
+     * Point at another location related to the error.
+     */
+    pub fn with_label(mut self, span: Span, message: String) -> Self {
+        self.labels.push(Label::new(span, message));
+        return self;
+    }
+}
+
+impl From<LexError> for ParseError {
+    fn from(error: LexError) -> Self {
+        return ParseError::new(error.code, error.message, error.span);
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(error: ParseError) -> Self {
+        return Diagnostic::new(error.code, error.message, error.span, error.labels);
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::Parser;
+    use crate::ast::{Expr, ExprKind, StmtKind};
+    use crate::diagnostic::ErrorCode;
+    use crate::lexer::{Dialect, Lexer};
+    use crate::{compile, CompileOptions, Emit, Output};
+
+    /**
+     * The expression assigned in "x = <source>", fully parenthesized.
+     */
+    fn grouping(source: &str) -> String {
+        let options = CompileOptions { dialect: Dialect::Lua, emit: Emit::Ast, ..CompileOptions::default() };
+        let Ok(Output::Ast(block)) = compile(&format!("x = {source}"), &options) else {
+            panic!("{source:?} should parse");
+        };
+        let StmtKind::Assign(_, values) = &block.stmts[0].kind else {
+            panic!("{source:?} should be an assignment");
+        };
+
+        return parenthesize(&values[0]);
+    }
+
+    fn parenthesize(expr: &Expr) -> String {
+        return match &expr.kind {
+            ExprKind::Unary(op, operand) => format!("({op} {})", parenthesize(operand)),
+            ExprKind::Binary(op, left, right) => format!("({} {op} {})", parenthesize(left), parenthesize(right)),
+            ExprKind::Paren(inner) => format!("[{}]", parenthesize(inner)),
+            ExprKind::Variable(variable) => variable.to_string(),
+            ExprKind::Integer(value) => value.to_string(),
+            _ => panic!("unexpected expression {expr:?}"),
+        };
+    }
+
+    #[test]
+    fn power_binds_tighter_than_unary_operators() {
+        assert_eq!(grouping("-x^2"), "(- (x ^ 2))");
+        assert_eq!(grouping("not a^b"), "(not (a ^ b))");
+        assert_eq!(grouping("2^-3"), "(2 ^ (- 3))");
+        assert_eq!(grouping("-a * b"), "((- a) * b)");
+    }
+
+    #[test]
+    fn power_and_concatenation_are_right_associative() {
+        assert_eq!(grouping("2^3^2"), "(2 ^ (3 ^ 2))");
+        assert_eq!(grouping("a..b..c"), "(a .. (b .. c))");
+        assert_eq!(grouping("a - b - c"), "((a - b) - c)");
+        assert_eq!(grouping("a // b % c"), "((a // b) % c)");
+    }
+
+    #[test]
+    fn parentheses_are_kept() {
+        assert_eq!(grouping("(a + b) * c"), "([(a + b)] * c)");
+        assert_eq!(grouping("-(2^2)"), "(- [(2 ^ 2)])");
+        assert_eq!(grouping("(a)"), "[a]");
+    }
+
+    #[test]
+    fn parenthesized_expression_is_not_a_variable() {
+        assert_eq!(errors("local x (x) = 1"), vec![ErrorCode::InvalidStatement]);
+        assert_eq!(errors("(x) = 1"), vec![ErrorCode::InvalidStatement]);
+        // The field of a parenthesized table is still a variable.
+        assert_eq!(errors("local t = {} (t).k = 1"), vec![]);
+    }
+
+    #[test]
+    fn not_binds_tighter_than_comparisons() {
+        assert_eq!(grouping("not a == b"), "((not a) == b)");
+        assert_eq!(grouping("a or b and c"), "(a or (b and c))");
+        assert_eq!(grouping("a < b and b < c"), "((a < b) and (b < c))");
+    }
+
+    #[test]
+    fn each_level_binds_tighter_than_the_previous() {
+        assert_eq!(grouping("a or b and c < d | e ~ f & g << h .. i + j * k ^ l"),
+            "(a or (b and (c < (d | (e ~ (f & (g << (h .. (i + (j * (k ^ l)))))))))))");
+        assert_eq!(grouping("a ^ b * c + d .. e << f & g ~ h | i < j and k or l"),
+            "(((((((((((a ^ b) * c) + d) .. e) << f) & g) ~ h) | i) < j) and k) or l)");
+    }
+
+    fn errors(source: &str) -> Vec<ErrorCode> {
+        let options = CompileOptions { dialect: Dialect::Lua, emit: Emit::Ast, ..CompileOptions::default() };
+        return match compile(source, &options) {
+            Ok(_) => Vec::new(),
+            Err(diagnostics) => diagnostics.iter().map(|diagnostic| diagnostic.code).collect(),
+        };
+    }
+
+    #[test]
+    fn syntax_errors_are_returned() {
+        let mut lexer = Lexer::new("local x = = 1", "test.lua");
+        lexer.set_dialect(Dialect::Lua);
+        let errors = Parser::new(lexer).program().unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, ErrorCode::UnexpectedToken);
+        assert_eq!((errors[0].span.line, errors[0].span.column), (1, 11));
+    }
+
+    #[test]
+    fn parser_recovers_and_reports_every_error() {
+        assert_eq!(errors("x = = 1\nlocal y = 2\nlocal z = )"), vec![ErrorCode::UnexpectedToken, ErrorCode::UnexpectedToken]);
+        assert_eq!(errors("local x = @\nlocal y = 1"), vec![ErrorCode::UnknownToken]);
+    }
+
+    #[test]
+    fn parser_gives_up_after_max_errors() {
+        let options = CompileOptions { dialect: Dialect::Lua, emit: Emit::Ast, max_errors: 2, ..CompileOptions::default() };
+        let Err(diagnostics) = compile("local x = =\nlocal y = =\nlocal z = =\n", &options) else {
+            panic!("should not compile");
+        };
+        let codes: Vec<ErrorCode> = diagnostics.iter().map(|diagnostic| diagnostic.code).collect();
+
+        assert_eq!(codes, vec![ErrorCode::UnexpectedToken, ErrorCode::UnexpectedToken, ErrorCode::TooManyErrors]);
+        assert_eq!(diagnostics.error_count(), 2);
+    }
+
+    #[test]
+    fn basic_parser_resumes_on_the_next_line() {
+        let options = CompileOptions { emit: Emit::Ast, ..CompileOptions::default() };
+        let Err(diagnostics) = compile("LET input = 1\nPRINT 2\n", &options) else {
+            panic!("should not compile");
+        };
+
+        assert_eq!(diagnostics.diagnostics.len(), 1);
+        assert_eq!(diagnostics.diagnostics[0].message, "Expected IDENT, got INPUT");
+    }
+
+    #[test]
+    fn missing_operand_names_the_end_of_the_input() {
+        let messages = |source: &str, dialect: Dialect| -> Vec<String> {
+            let options = CompileOptions { dialect, emit: Emit::Ast, ..CompileOptions::default() };
+            let Err(diagnostics) = compile(source, &options) else {
+                panic!("{source:?} should not compile");
+            };
+            return diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect();
+        };
+
+        assert_eq!(messages("x = ", Dialect::Lua), vec!["Unexpected end of input"]);
+        assert_eq!(messages("x = 1 +", Dialect::Lua), vec!["Unexpected end of input"]);
+        assert_eq!(messages("x = )", Dialect::Lua), vec!["Unexpected token at )"]);
+        assert_eq!(messages("LET x =", Dialect::Basic), vec!["Unexpected end of line"]);
+        assert_eq!(messages("LET x = 1 +\nPRINT x", Dialect::Basic), vec!["Unexpected end of line"]);
+    }
+
+    #[test]
+    fn assignment_takes_lists_of_variables_and_values() {
+        let options = CompileOptions { dialect: Dialect::Lua, emit: Emit::Ast, ..CompileOptions::default() };
+        let Ok(Output::Ast(block)) = compile("a, b = b, a\nt.x, t[1], u = 1, 2", &options) else {
+            panic!("should parse");
+        };
+
+        let counts: Vec<(usize, usize)> = block.stmts.iter()
+            .map(|stmt| match &stmt.kind {
+                StmtKind::Assign(targets, values) => (targets.len(), values.len()),
+                _ => panic!("{stmt:?} should be an assignment"),
+            })
+            .collect();
+        assert_eq!(counts, vec![(2, 2), (3, 2)]);
+    }
+
+    #[test]
+    fn only_variables_and_fields_can_be_assigned() {
+        assert_eq!(errors("a, f() = 1, 2"), vec![ErrorCode::InvalidStatement]);
+        assert_eq!(errors("f(), a = 1, 2"), vec![ErrorCode::InvalidStatement]);
+        assert_eq!(errors("a, (b) = 1, 2"), vec![ErrorCode::InvalidStatement]);
+        assert_eq!(errors("a, b"), vec![ErrorCode::UnexpectedToken]);
+    }
+
+    #[test]
+    fn goto_cannot_jump_into_the_scope_of_a_local() {
+        assert_eq!(errors("do goto l end local x = 5 ::l:: print(x)"), vec![ErrorCode::JumpIntoScope]);
+        assert_eq!(errors("goto l local x ::l:: x = 1"), vec![ErrorCode::JumpIntoScope]);
+    }
+
+    #[test]
+    fn goto_can_jump_to_a_label_ending_the_block() {
+        assert_eq!(errors("while true do goto continue local x = 5 ::continue:: end"), vec![]);
+        assert_eq!(errors("do goto l local x = 5 ::l:: end"), vec![]);
+        assert_eq!(errors("do goto l; local x ::l:: ; end"), vec![]);
+        assert_eq!(errors("do goto l local x ::l:: ::m:: ; ::n:: end"), vec![]);
+        assert_eq!(errors("goto l local x ::l:: ;;"), vec![]);
+    }
+
+    #[test]
+    fn label_followed_by_a_statement_does_not_end_the_block() {
+        assert_eq!(errors("do goto l local x ::l:: ; ::m:: x = 1 end"), vec![ErrorCode::JumpIntoScope]);
+        assert_eq!(errors("do goto l local x ::l:: ; return end"), vec![ErrorCode::JumpIntoScope]);
+        assert_eq!(errors("do goto l local x ::l:: do end end"), vec![ErrorCode::JumpIntoScope]);
+    }
+
+    #[test]
+    fn goto_can_jump_past_locals_of_a_closed_block() {
+        assert_eq!(errors("goto l do local x = 5 end ::l:: print(1)"), vec![]);
+    }
+}
@@ -0,0 +1,796 @@
+/* Runtime of the generated program: dynamically typed Lua values and the operators on them.
+ * Memory is never freed, the program just ends. */
+#include <inttypes.h>
+#include <math.h>
+#include <stdarg.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef enum {
+    LUA_NIL,
+    LUA_BOOLEAN,
+    LUA_INTEGER,
+    LUA_FLOAT,
+    LUA_STRING,
+    LUA_FUNCTION,
+    LUA_TABLE,
+} LuaType;
+
+typedef struct Value Value;
+typedef struct Closure Closure;
+typedef struct Table Table;
+
+/* Code of a function: gets its closure and arguments, pushes its results on the stack and returns how many. */
+typedef int (*LuaFunction)(Closure *closure, int argc, Value *argv);
+
+/* A function value: its code and the cells of the locals of enclosing functions it uses. */
+struct Closure {
+    LuaFunction code;
+    Value *upvalues[];
+};
+
+struct Value {
+    LuaType type;
+    union {
+        int boolean;
+        int64_t integer;
+        double number;
+        /* Strings are byte arrays with a length, they can hold '\0's. */
+        struct {
+            const char *data;
+            size_t length;
+        } string;
+        Closure *function;
+        Table *table;
+    } as;
+};
+
+Value lua_nil(void) {
+    Value value;
+    value.type = LUA_NIL;
+    return value;
+}
+
+Value lua_boolean(int boolean) {
+    Value value;
+    value.type = LUA_BOOLEAN;
+    value.as.boolean = boolean != 0;
+    return value;
+}
+
+Value lua_integer(int64_t integer) {
+    Value value;
+    value.type = LUA_INTEGER;
+    value.as.integer = integer;
+    return value;
+}
+
+Value lua_float(double number) {
+    Value value;
+    value.type = LUA_FLOAT;
+    value.as.number = number;
+    return value;
+}
+
+Value lua_string(const char *data, size_t length) {
+    Value value;
+    value.type = LUA_STRING;
+    value.as.string.data = data;
+    value.as.string.length = length;
+    return value;
+}
+
+void lua_error(const char *format, ...) {
+    va_list args;
+    va_start(args, format);
+    fputs("lua: ", stderr);
+    vfprintf(stderr, format, args);
+    fputc('\n', stderr);
+    va_end(args);
+    exit(1);
+}
+
+/* New closure of code over count cells, given after it. */
+Value lua_closure(LuaFunction code, int count, ...) {
+    Closure *closure = malloc(sizeof(Closure) + (size_t)count * sizeof(Value *));
+    if (closure == NULL) lua_error("not enough memory");
+
+    va_list cells;
+    va_start(cells, count);
+    closure->code = code;
+    for (int i = 0; i < count; i++) {
+        closure->upvalues[i] = va_arg(cells, Value *);
+    }
+    va_end(cells);
+
+    Value value;
+    value.type = LUA_FUNCTION;
+    value.as.function = closure;
+    return value;
+}
+
+/* Cell holding a local that closures share, living as long as they do. */
+Value *lua_cell(Value value) {
+    Value *cell = malloc(sizeof(Value));
+    if (cell == NULL) lua_error("not enough memory");
+    *cell = value;
+    return cell;
+}
+
+const char *lua_typename(Value value) {
+    switch (value.type) {
+    case LUA_NIL: return "nil";
+    case LUA_BOOLEAN: return "boolean";
+    case LUA_INTEGER:
+    case LUA_FLOAT: return "number";
+    case LUA_STRING: return "string";
+    case LUA_FUNCTION: return "function";
+    case LUA_TABLE: return "table";
+    }
+    return "?";
+}
+
+int lua_truthy(Value value) {
+    return !(value.type == LUA_NIL || (value.type == LUA_BOOLEAN && !value.as.boolean));
+}
+
+/* Stack of the values passed around in calls: a function, its arguments on top of it, and then the
+ * results it returns in their place. Lists like the values of a return are gathered on it too. */
+#define LUA_STACK_SIZE 100000
+#define LUA_MAX_DEPTH 10000
+
+static Value lua_stack[LUA_STACK_SIZE];
+static int lua_top = 0;
+static int lua_depth = 0;
+
+void lua_push(Value value) {
+    if (lua_top == LUA_STACK_SIZE) {
+        lua_error("stack overflow");
+    }
+    lua_stack[lua_top++] = value;
+}
+
+/* Drop everything above base. */
+void lua_settop(int base) {
+    lua_top = base;
+}
+
+/* Value number index of the list starting at base, nil past its end. */
+Value lua_get(int base, int index) {
+    if (base + index < lua_top) {
+        return lua_stack[base + index];
+    }
+    return lua_nil();
+}
+
+/* Call the function at base with the arguments above it. All its results take their place, the
+ * function included, and their number is returned. */
+int lua_call(int base) {
+    Value function = lua_stack[base];
+    if (function.type != LUA_FUNCTION) {
+        lua_error("attempt to call a %s value", lua_typename(function));
+    }
+    if (lua_depth == LUA_MAX_DEPTH) {
+        lua_error("stack overflow");
+    }
+
+    lua_depth++;
+    int count = function.as.function->code(function.as.function, lua_top - base - 1, &lua_stack[base + 1]);
+    lua_depth--;
+
+    memmove(&lua_stack[base], &lua_stack[lua_top - count], (size_t)count * sizeof(Value));
+    lua_top = base + count;
+    return count;
+}
+
+/* Call, keeping only the first result. */
+Value lua_call1(int base) {
+    Value result = lua_call(base) > 0 ? lua_stack[base] : lua_nil();
+    lua_top = base;
+    return result;
+}
+
+/* Return everything pushed since base. */
+int lua_return(int base) {
+    return lua_top - base;
+}
+
+/* A parameter, nil if the caller passed fewer arguments. */
+Value lua_arg(int argc, Value *argv, int index) {
+    return index < argc ? argv[index] : lua_nil();
+}
+
+/* Number held by a string, the way Lua converts strings in arithmetic. */
+static int lua_string_to_number(Value value, Value *number) {
+    char buffer[64];
+    char *end;
+    size_t length = value.as.string.length;
+
+    if (length >= sizeof buffer || memchr(value.as.string.data, '\0', length)) {
+        return 0;
+    }
+    memcpy(buffer, value.as.string.data, length);
+    buffer[length] = '\0';
+
+    /* Unlike C, a leading 0 doesn't make it octal. */
+    const char *digits = buffer + strspn(buffer, " \t\n\r");
+    if (*digits == '-' || *digits == '+') digits++;
+    int base = (digits[0] == '0' && (digits[1] == 'x' || digits[1] == 'X')) ? 16 : 10;
+
+    long long integer = strtoll(buffer, &end, base);
+    while (*end == ' ' || *end == '\t' || *end == '\n' || *end == '\r') end++;
+    if (end != buffer && *end == '\0' && !strpbrk(buffer, "nN")) {
+        *number = lua_integer(integer);
+        return 1;
+    }
+
+    double real = strtod(buffer, &end);
+    while (*end == ' ' || *end == '\t' || *end == '\n' || *end == '\r') end++;
+    if (end != buffer && *end == '\0' && !strpbrk(buffer, "nN")) {
+        *number = lua_float(real);
+        return 1;
+    }
+
+    return 0;
+}
+
+static int lua_tonumber(Value value, Value *number) {
+    if (value.type == LUA_INTEGER || value.type == LUA_FLOAT) {
+        *number = value;
+        return 1;
+    }
+    if (value.type == LUA_STRING) {
+        return lua_string_to_number(value, number);
+    }
+    return 0;
+}
+
+double lua_tofloat(Value value) {
+    Value number;
+    if (!lua_tonumber(value, &number)) {
+        lua_error("attempt to perform arithmetic on a %s value", lua_typename(value));
+    }
+    return number.type == LUA_INTEGER ? (double)number.as.integer : number.as.number;
+}
+
+static void lua_arith_operands(Value a, Value b, Value *x, Value *y) {
+    if (!lua_tonumber(a, x)) {
+        lua_error("attempt to perform arithmetic on a %s value", lua_typename(a));
+    }
+    if (!lua_tonumber(b, y)) {
+        lua_error("attempt to perform arithmetic on a %s value", lua_typename(b));
+    }
+}
+
+static int lua_both_integers(Value x, Value y) {
+    return x.type == LUA_INTEGER && y.type == LUA_INTEGER;
+}
+
+/* Integer arithmetic wraps around, like in Lua. */
+Value lua_add(Value a, Value b) {
+    Value x, y;
+    lua_arith_operands(a, b, &x, &y);
+    if (lua_both_integers(x, y)) return lua_integer((int64_t)((uint64_t)x.as.integer + (uint64_t)y.as.integer));
+    return lua_float(lua_tofloat(x) + lua_tofloat(y));
+}
+
+Value lua_sub(Value a, Value b) {
+    Value x, y;
+    lua_arith_operands(a, b, &x, &y);
+    if (lua_both_integers(x, y)) return lua_integer((int64_t)((uint64_t)x.as.integer - (uint64_t)y.as.integer));
+    return lua_float(lua_tofloat(x) - lua_tofloat(y));
+}
+
+Value lua_mul(Value a, Value b) {
+    Value x, y;
+    lua_arith_operands(a, b, &x, &y);
+    if (lua_both_integers(x, y)) return lua_integer((int64_t)((uint64_t)x.as.integer * (uint64_t)y.as.integer));
+    return lua_float(lua_tofloat(x) * lua_tofloat(y));
+}
+
+Value lua_div(Value a, Value b) {
+    Value x, y;
+    lua_arith_operands(a, b, &x, &y);
+    return lua_float(lua_tofloat(x) / lua_tofloat(y));
+}
+
+Value lua_pow(Value a, Value b) {
+    Value x, y;
+    lua_arith_operands(a, b, &x, &y);
+    return lua_float(pow(lua_tofloat(x), lua_tofloat(y)));
+}
+
+/* Floor division and modulo round towards minus infinity. */
+Value lua_idiv(Value a, Value b) {
+    Value x, y;
+    lua_arith_operands(a, b, &x, &y);
+    if (lua_both_integers(x, y)) {
+        int64_t n = x.as.integer, d = y.as.integer;
+        if (d == 0) lua_error("attempt to perform 'n//0'");
+        if (d == -1) return lua_integer((int64_t)(0u - (uint64_t)n));
+        int64_t q = n / d;
+        if (n % d != 0 && (n ^ d) < 0) q -= 1;
+        return lua_integer(q);
+    }
+    return lua_float(floor(lua_tofloat(x) / lua_tofloat(y)));
+}
+
+Value lua_mod(Value a, Value b) {
+    Value x, y;
+    lua_arith_operands(a, b, &x, &y);
+    if (lua_both_integers(x, y)) {
+        int64_t n = x.as.integer, d = y.as.integer;
+        if (d == 0) lua_error("attempt to perform 'n%%0'");
+        if (d == -1) return lua_integer(0);
+        int64_t m = n % d;
+        if (m != 0 && (m ^ d) < 0) m += d;
+        return lua_integer(m);
+    }
+    double n = lua_tofloat(x), d = lua_tofloat(y);
+    double m = fmod(n, d);
+    if ((m > 0) ? d < 0 : (m < 0 && d != m)) m += d;
+    return lua_float(m);
+}
+
+Value lua_unm(Value a) {
+    Value x;
+    if (!lua_tonumber(a, &x)) {
+        lua_error("attempt to perform arithmetic on a %s value", lua_typename(a));
+    }
+    if (x.type == LUA_INTEGER) return lua_integer((int64_t)(0u - (uint64_t)x.as.integer));
+    return lua_float(-x.as.number);
+}
+
+/* Bitwise operators need integers; floats only if they have an exact integer value. */
+static int64_t lua_bitwise_operand(Value value) {
+    Value number;
+    if (!lua_tonumber(value, &number)) {
+        lua_error("attempt to perform bitwise operation on a %s value", lua_typename(value));
+    }
+    if (number.type == LUA_INTEGER) {
+        return number.as.integer;
+    }
+    double real = number.as.number;
+    if (real >= -9223372036854775808.0 && real < 9223372036854775808.0 && real == floor(real)) {
+        return (int64_t)real;
+    }
+    lua_error("number has no integer representation");
+    return 0;
+}
+
+Value lua_band(Value a, Value b) {
+    return lua_integer(lua_bitwise_operand(a) & lua_bitwise_operand(b));
+}
+
+Value lua_bor(Value a, Value b) {
+    return lua_integer(lua_bitwise_operand(a) | lua_bitwise_operand(b));
+}
+
+Value lua_bxor(Value a, Value b) {
+    return lua_integer(lua_bitwise_operand(a) ^ lua_bitwise_operand(b));
+}
+
+Value lua_bnot(Value a) {
+    return lua_integer(~lua_bitwise_operand(a));
+}
+
+/* Logical shifts; shifting by 64 bits or more gives 0. */
+static int64_t lua_shift_left(int64_t x, int64_t y) {
+    if (y <= -64 || y >= 64) return 0;
+    if (y >= 0) return (int64_t)((uint64_t)x << y);
+    return (int64_t)((uint64_t)x >> -y);
+}
+
+Value lua_shl(Value a, Value b) {
+    return lua_integer(lua_shift_left(lua_bitwise_operand(a), lua_bitwise_operand(b)));
+}
+
+Value lua_shr(Value a, Value b) {
+    int64_t y = lua_bitwise_operand(b);
+    return lua_integer(lua_shift_left(lua_bitwise_operand(a), y == INT64_MIN ? 64 : -y));
+}
+
+static int lua_is_number(Value value) {
+    return value.type == LUA_INTEGER || value.type == LUA_FLOAT;
+}
+
+static int lua_raw_equal(Value a, Value b) {
+    if (lua_both_integers(a, b)) return a.as.integer == b.as.integer;
+    if (lua_is_number(a) && lua_is_number(b)) return lua_tofloat(a) == lua_tofloat(b);
+    if (a.type != b.type) return 0;
+
+    switch (a.type) {
+    case LUA_NIL: return 1;
+    case LUA_BOOLEAN: return a.as.boolean == b.as.boolean;
+    case LUA_FUNCTION: return a.as.function == b.as.function;
+    case LUA_TABLE: return a.as.table == b.as.table;
+    case LUA_STRING:
+        return a.as.string.length == b.as.string.length
+            && memcmp(a.as.string.data, b.as.string.data, a.as.string.length) == 0;
+    default: return 0;
+    }
+}
+
+Value lua_eq(Value a, Value b) {
+    return lua_boolean(lua_raw_equal(a, b));
+}
+
+Value lua_ne(Value a, Value b) {
+    return lua_boolean(!lua_raw_equal(a, b));
+}
+
+/* Negative, zero or positive like strcmp; numbers and strings only. */
+static int lua_compare(Value a, Value b) {
+    if (lua_both_integers(a, b)) {
+        return (a.as.integer > b.as.integer) - (a.as.integer < b.as.integer);
+    }
+    if (lua_is_number(a) && lua_is_number(b)) {
+        double x = lua_tofloat(a), y = lua_tofloat(b);
+        return (x > y) - (x < y);
+    }
+    if (a.type == LUA_STRING && b.type == LUA_STRING) {
+        size_t length = a.as.string.length < b.as.string.length ? a.as.string.length : b.as.string.length;
+        int order = memcmp(a.as.string.data, b.as.string.data, length);
+        if (order != 0) return order;
+        return (a.as.string.length > b.as.string.length) - (a.as.string.length < b.as.string.length);
+    }
+
+    if (a.type == b.type || (lua_is_number(a) && lua_is_number(b))) {
+        lua_error("attempt to compare two %s values", lua_typename(a));
+    }
+    lua_error("attempt to compare %s with %s", lua_typename(a), lua_typename(b));
+    return 0;
+}
+
+Value lua_lt(Value a, Value b) {
+    return lua_boolean(lua_compare(a, b) < 0);
+}
+
+Value lua_le(Value a, Value b) {
+    return lua_boolean(lua_compare(a, b) <= 0);
+}
+
+Value lua_gt(Value a, Value b) {
+    return lua_boolean(lua_compare(a, b) > 0);
+}
+
+Value lua_ge(Value a, Value b) {
+    return lua_boolean(lua_compare(a, b) >= 0);
+}
+
+Value lua_not(Value a) {
+    return lua_boolean(!lua_truthy(a));
+}
+
+/* Tables keep the values of the keys 1 to array_count in an array, and every other key in a hash
+ * part found by open addressing. Keys are never removed from the hash part, their value becomes nil. */
+typedef struct {
+    Value key;
+    Value value;
+} TableNode;
+
+struct Table {
+    Value *array;
+    int64_t array_count;
+    int64_t array_capacity;
+    /* A node whose key is nil is free; the capacity is 0 or a power of two. */
+    TableNode *nodes;
+    size_t node_capacity;
+    size_t node_count;
+};
+
+Value lua_table(void) {
+    Table *table = calloc(1, sizeof(Table));
+    if (table == NULL) lua_error("not enough memory");
+
+    Value value;
+    value.type = LUA_TABLE;
+    value.as.table = table;
+    return value;
+}
+
+/* Floats with an integer value are the same key as that integer. */
+static Value lua_normalize_key(Value key) {
+    if (key.type == LUA_FLOAT) {
+        double real = key.as.number;
+        if (real >= -9223372036854775808.0 && real < 9223372036854775808.0 && real == floor(real)) {
+            return lua_integer((int64_t)real);
+        }
+    }
+    return key;
+}
+
+static uint64_t lua_hash(Value key) {
+    uint64_t hash = 0;
+
+    switch (key.type) {
+    case LUA_BOOLEAN: hash = (uint64_t)key.as.boolean; break;
+    case LUA_INTEGER: hash = (uint64_t)key.as.integer; break;
+    case LUA_FLOAT: memcpy(&hash, &key.as.number, sizeof hash); break;
+    case LUA_STRING:
+        /* FNV-1a */
+        hash = 14695981039346656037u;
+        for (size_t i = 0; i < key.as.string.length; i++) {
+            hash = (hash ^ (unsigned char)key.as.string.data[i]) * 1099511628211u;
+        }
+        break;
+    case LUA_FUNCTION: hash = (uint64_t)(uintptr_t)key.as.function; break;
+    case LUA_TABLE: hash = (uint64_t)(uintptr_t)key.as.table; break;
+    case LUA_NIL: break;
+    }
+
+    /* Spread the bits, the low ones pick the node. */
+    return hash * 11400714819323198485u;
+}
+
+static TableNode *lua_find_node(Table *table, Value key) {
+    if (table->node_capacity == 0) return NULL;
+
+    size_t mask = table->node_capacity - 1;
+    for (size_t i = (size_t)lua_hash(key) & mask; table->nodes[i].key.type != LUA_NIL; i = (i + 1) & mask) {
+        if (lua_raw_equal(table->nodes[i].key, key)) {
+            return &table->nodes[i];
+        }
+    }
+    return NULL;
+}
+
+static void lua_insert_node(Table *table, Value key, Value value) {
+    size_t mask = table->node_capacity - 1;
+    size_t i = (size_t)lua_hash(key) & mask;
+    while (table->nodes[i].key.type != LUA_NIL) {
+        i = (i + 1) & mask;
+    }
+    table->nodes[i].key = key;
+    table->nodes[i].value = value;
+    table->node_count++;
+}
+
+/* Double the hash part, dropping the keys whose value is nil. */
+static void lua_grow_nodes(Table *table) {
+    TableNode *nodes = table->nodes;
+    size_t capacity = table->node_capacity;
+
+    table->node_capacity = capacity == 0 ? 4 : capacity * 2;
+    table->nodes = calloc(table->node_capacity, sizeof(TableNode));
+    if (table->nodes == NULL) lua_error("not enough memory");
+    table->node_count = 0;
+
+    for (size_t i = 0; i < capacity; i++) {
+        if (nodes[i].key.type != LUA_NIL && nodes[i].value.type != LUA_NIL) {
+            lua_insert_node(table, nodes[i].key, nodes[i].value);
+        }
+    }
+    free(nodes);
+}
+
+static void lua_hash_set(Table *table, Value key, Value value) {
+    TableNode *node = lua_find_node(table, key);
+    if (node != NULL) {
+        node->value = value;
+        return;
+    }
+    if (value.type == LUA_NIL) return;
+
+    /* Keep at most three quarters of the nodes used. */
+    if ((table->node_count + 1) * 4 > table->node_capacity * 3) {
+        lua_grow_nodes(table);
+    }
+    lua_insert_node(table, key, value);
+}
+
+static void lua_array_append(Table *table, Value value) {
+    if (table->array_count == table->array_capacity) {
+        table->array_capacity = table->array_capacity == 0 ? 4 : table->array_capacity * 2;
+        table->array = realloc(table->array, (size_t)table->array_capacity * sizeof(Value));
+        if (table->array == NULL) lua_error("not enough memory");
+    }
+    table->array[table->array_count++] = value;
+}
+
+Value lua_rawget(Table *table, Value key) {
+    key = lua_normalize_key(key);
+    if (key.type == LUA_INTEGER && key.as.integer >= 1 && key.as.integer <= table->array_count) {
+        return table->array[key.as.integer - 1];
+    }
+
+    TableNode *node = lua_find_node(table, key);
+    return node != NULL ? node->value : lua_nil();
+}
+
+void lua_rawset(Table *table, Value key, Value value) {
+    if (key.type == LUA_NIL) lua_error("index is nil");
+    if (key.type == LUA_FLOAT && key.as.number != key.as.number) lua_error("index is NaN");
+    key = lua_normalize_key(key);
+
+    if (key.type == LUA_INTEGER && key.as.integer >= 1 && key.as.integer <= table->array_count) {
+        table->array[key.as.integer - 1] = value;
+        /* The array part ends with its last non-nil value, so its size is a border for #. */
+        while (table->array_count > 0 && table->array[table->array_count - 1].type == LUA_NIL) {
+            table->array_count--;
+        }
+        return;
+    }
+
+    if (key.type == LUA_INTEGER && key.as.integer == table->array_count + 1 && value.type != LUA_NIL) {
+        lua_hash_set(table, key, lua_nil());
+        lua_array_append(table, value);
+
+        /* Move the keys that now follow the array part from the hash part. */
+        for (;;) {
+            TableNode *next = lua_find_node(table, lua_integer(table->array_count + 1));
+            if (next == NULL || next->value.type == LUA_NIL) break;
+            lua_array_append(table, next->value);
+            next->value = lua_nil();
+        }
+        return;
+    }
+
+    lua_hash_set(table, key, value);
+}
+
+Value lua_index(Value table, Value key) {
+    if (table.type != LUA_TABLE) {
+        lua_error("attempt to index a %s value", lua_typename(table));
+    }
+    return lua_rawget(table.as.table, key);
+}
+
+void lua_setindex(Value table, Value key, Value value) {
+    if (table.type != LUA_TABLE) {
+        lua_error("attempt to index a %s value", lua_typename(table));
+    }
+    lua_rawset(table.as.table, key, value);
+}
+
+/* Store the values pushed since base at the keys first, first + 1, ... and drop them. */
+void lua_setlist(Value table, int64_t first, int base) {
+    for (int i = base; i < lua_top; i++) {
+        lua_rawset(table.as.table, lua_integer(first + (i - base)), lua_stack[i]);
+    }
+    lua_top = base;
+}
+
+/* Text of a number the way Lua prints it: floats always show they are floats, e.g. 1.0. */
+static size_t lua_number_text(Value number, char *buffer, size_t size) {
+    if (number.type == LUA_INTEGER) {
+        return (size_t)snprintf(buffer, size, "%" PRId64, number.as.integer);
+    }
+
+    size_t length = (size_t)snprintf(buffer, size, "%.14g", number.as.number);
+    if (strspn(buffer, "-0123456789") == length) {
+        length += (size_t)snprintf(buffer + length, size - length, ".0");
+    }
+    return length;
+}
+
+/* Bytes of a string or number operand, or 0 if it's neither. */
+static int lua_concat_operand(Value value, char *buffer, size_t size, const char **data, size_t *length) {
+    if (value.type == LUA_STRING) {
+        *data = value.as.string.data;
+        *length = value.as.string.length;
+        return 1;
+    }
+    if (lua_is_number(value)) {
+        *length = lua_number_text(value, buffer, size);
+        *data = buffer;
+        return 1;
+    }
+    return 0;
+}
+
+Value lua_concat(Value a, Value b) {
+    char a_buffer[64], b_buffer[64];
+    const char *a_data, *b_data;
+    size_t a_length, b_length;
+
+    if (!lua_concat_operand(a, a_buffer, sizeof a_buffer, &a_data, &a_length)) {
+        lua_error("attempt to concatenate a %s value", lua_typename(a));
+    }
+    if (!lua_concat_operand(b, b_buffer, sizeof b_buffer, &b_data, &b_length)) {
+        lua_error("attempt to concatenate a %s value", lua_typename(b));
+    }
+
+    char *data = malloc(a_length + b_length + 1);
+    if (data == NULL) lua_error("not enough memory");
+    memcpy(data, a_data, a_length);
+    memcpy(data + a_length, b_data, b_length);
+    data[a_length + b_length] = '\0';
+    return lua_string(data, a_length + b_length);
+}
+
+Value lua_len(Value a) {
+    if (a.type == LUA_STRING) {
+        return lua_integer((int64_t)a.as.string.length);
+    }
+    if (a.type == LUA_TABLE) {
+        return lua_integer(a.as.table->array_count);
+    }
+    lua_error("attempt to get length of a %s value", lua_typename(a));
+    return lua_nil();
+}
+
+/* Number typed in by the user, 0 if what was typed isn't a number. */
+Value lua_read_number(void) {
+    double number;
+    if (scanf("%lf", &number) != 1) {
+        number = 0;
+        if (scanf("%*s") == EOF) clearerr(stdin);
+    }
+    return lua_float(number);
+}
+
+/* The print function: its arguments separated by tabs, the way tostring writes them. */
+int lua_print(Closure *closure, int argc, Value *argv) {
+    char buffer[64];
+    const char *data;
+    size_t length;
+
+    (void)closure;
+    for (int i = 0; i < argc; i++) {
+        if (i > 0) putchar('\t');
+
+        Value value = argv[i];
+        switch (value.type) {
+        case LUA_NIL: fputs("nil", stdout); break;
+        case LUA_BOOLEAN: fputs(value.as.boolean ? "true" : "false", stdout); break;
+        case LUA_FUNCTION: printf("function: %p", (void *)value.as.function); break;
+        case LUA_TABLE: printf("table: %p", (void *)value.as.table); break;
+        default:
+            lua_concat_operand(value, buffer, sizeof buffer, &data, &length);
+            fwrite(data, 1, length, stdout);
+        }
+    }
+    putchar('\n');
+    return 0;
+}
+
+Closure lua_print_closure = { lua_print };
+
+/* table.insert(t, value) appends, table.insert(t, position, value) moves the values from position on up first. */
+int lua_table_insert(Closure *closure, int argc, Value *argv) {
+    (void)closure;
+
+    if (argc < 1 || argv[0].type != LUA_TABLE) {
+        lua_error("bad argument #1 to 'insert' (table expected, got %s)", argc < 1 ? "no value" : lua_typename(argv[0]));
+    }
+    Table *table = argv[0].as.table;
+    int64_t end = table->array_count + 1;
+
+    if (argc == 2) {
+        lua_rawset(table, lua_integer(end), argv[1]);
+        return 0;
+    }
+    if (argc != 3) {
+        lua_error("wrong number of arguments to 'insert'");
+    }
+
+    Value position = lua_normalize_key(argv[1]);
+    if (position.type != LUA_INTEGER) {
+        lua_error("bad argument #2 to 'insert' (number has no integer representation)");
+    }
+    if (position.as.integer < 1 || position.as.integer > end) {
+        lua_error("bad argument #2 to 'insert' (position out of bounds)");
+    }
+
+    for (int64_t i = end; i > position.as.integer; i--) {
+        lua_rawset(table, lua_integer(i), lua_rawget(table, lua_integer(i - 1)));
+    }
+    lua_rawset(table, position, argv[2]);
+    return 0;
+}
+
+Closure lua_table_insert_closure = { lua_table_insert };
+
+/* The table library, filled in by lua_open. */
+Table lua_table_library;
+
+/* Set up what the runtime can't initialize statically, before the program runs. */
+void lua_open(void) {
+    Value insert;
+    insert.type = LUA_FUNCTION;
+    insert.as.function = &lua_table_insert_closure;
+    lua_rawset(&lua_table_library, lua_string("insert", 6), insert);
+}
@@ -0,0 +1,34 @@
+use std::fmt;
+use std::rc::Rc;
+
+/**
+ * Location of a piece of source code: the file it came from, the line and column where it
+ * starts (both 1-based) and the byte range it covers in the source text.
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Span {
+    pub file: Rc<str>,
+    pub line: usize,
+    pub column: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(file: Rc<str>, line: usize, column: usize, start: usize, end: usize) -> Self {
+        return Span { file, line, column, start, end };
+    }
+
+    /**
+     * Span starting where this one starts and ending where the other one ends.
+     */
+    pub fn to(&self, other: &Span) -> Span {
+        return Span::new(self.file.clone(), self.line, self.column, self.start, other.end.max(self.end));
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
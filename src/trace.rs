@@ -0,0 +1,78 @@
+use crate::lexer::Token;
+
+/**
+ * Environment variable enabling traces without command line flags, e.g. LUA_COMPILER_TRACE=parser,lexer
+ */
+pub const TRACE_ENV: &str = "LUA_COMPILER_TRACE";
+
+/**
+ * Opt-in debug trace of a compiler stage, written to stderr and indented by recursion depth.
+ */
+pub struct Tracer {
+    stage: &'static str,
+    enabled: bool,
+    depth: usize,
+}
+
+impl Tracer {
+    pub fn new(stage: &'static str, enabled: bool) -> Self {
+        return Tracer { stage, enabled, depth: 0 };
+    }
+
+    pub fn disabled(stage: &'static str) -> Self {
+        return Tracer::new(stage, false);
+    }
+
+    /**
+     * True when the stage is listed in the trace environment variable (or it says "all").
+     */
+    pub fn enabled_by_env(stage: &str) -> bool {
+        return std::env::var(TRACE_ENV)
+            .map(|stages| stages.split(',').any(|name| name.trim() == stage || name.trim() == "all"))
+            .unwrap_or(false);
+    }
+
+    /**
+     * Trace something happening at the current depth.
+     */
+    pub fn event(&self, what: &str, token: &Token) -> () {
+        if self.enabled {
+            eprintln!("[{}] {}{what} {} {:?} @ {}", self.stage, "  ".repeat(self.depth), token.ttype, token.text, token.span);
+        }
+    }
+
+    /**
+     * Trace the start of a rule; everything until the matching exit is indented one level deeper.
+     */
+    pub fn enter(&mut self, rule: &str, token: &Token) -> () {
+        self.event(rule, token);
+        self.depth += 1;
+    }
+
+    pub fn exit(&mut self) -> () {
+        self.depth = self.depth.saturating_sub(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tracer;
+    use crate::lexer::{Token, TokenType};
+
+    #[test]
+    fn exit_undoes_enter() {
+        let token = Token::new("x", TokenType::IDENT);
+        let mut tracer = Tracer::new("test", true);
+
+        tracer.enter("outer", &token);
+        tracer.enter("inner", &token);
+        assert_eq!(tracer.depth, 2);
+        tracer.exit();
+        tracer.enter("sibling", &token);
+        assert_eq!(tracer.depth, 2);
+        tracer.exit();
+        tracer.exit();
+        assert_eq!(tracer.depth, 0);
+    }
+}
+
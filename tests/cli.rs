@@ -0,0 +1,167 @@
+#![allow(clippy::needless_return)]
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/**
+ * A fresh directory for one test's files.
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lua-compiler-cli-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    return dir;
+}
+
+fn run(dir: &PathBuf, args: &[&str]) -> Output {
+    return Command::new(env!("CARGO_BIN_EXE_lua-compiler"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .expect("should run the compiler");
+}
+
+#[test]
+fn exit_codes() {
+    let dir = scratch_dir("exit-codes");
+    fs::write(dir.join("good.lua"), "print(1)\n").unwrap();
+    fs::write(dir.join("bad.lua"), "x = = 1\n").unwrap();
+
+    assert_eq!(run(&dir, &["good.lua"]).status.code(), Some(0));
+    assert!(dir.join("good.c").exists());
+    assert_eq!(run(&dir, &["bad.lua"]).status.code(), Some(1));
+    assert_eq!(run(&dir, &["--bogus", "good.lua"]).status.code(), Some(2));
+    assert_eq!(run(&dir, &[]).status.code(), Some(2));
+    assert_eq!(run(&dir, &["missing.lua"]).status.code(), Some(3));
+    assert_eq!(run(&dir, &["good.lua", "-o", "good.lua/out.c"]).status.code(), Some(3));
+
+    // Every input is compiled, the first failure decides the exit code.
+    assert_eq!(run(&dir, &["missing.lua", "bad.lua", "good.lua"]).status.code(), Some(3));
+    assert_eq!(run(&dir, &["bad.lua", "missing.lua"]).status.code(), Some(1));
+}
+
+#[test]
+fn sarif_for_several_inputs_is_one_log() {
+    let dir = scratch_dir("sarif");
+    fs::write(dir.join("a.lua"), "x = = 1\n").unwrap();
+    fs::write(dir.join("b.lua"), "goto nowhere\n").unwrap();
+
+    let output = run(&dir, &["-q", "--error-format=sarif", "a.lua", "b.lua"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let log: serde_json::Value = serde_json::from_slice(&output.stderr).expect("should be a single JSON document");
+    let runs = log["runs"].as_array().unwrap();
+    assert_eq!(runs.len(), 1);
+
+    let results = runs[0]["results"].as_array().unwrap();
+    let files: Vec<&str> = results.iter()
+        .map(|result| result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"].as_str().unwrap())
+        .collect();
+    assert_eq!(files, vec!["a.lua", "b.lua"]);
+}
+
+#[test]
+fn json_for_several_inputs_is_one_object_per_line() {
+    let dir = scratch_dir("json");
+    fs::write(dir.join("a.lua"), "x = = 1\n").unwrap();
+    fs::write(dir.join("b.lua"), "goto nowhere\n").unwrap();
+
+    let output = run(&dir, &["-q", "--error-format=json", "a.lua", "b.lua"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let files: Vec<String> = stderr.lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).expect("each line should be JSON"))
+        .map(|diagnostic| diagnostic["span"]["file"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(files, vec!["a.lua", "b.lua"]);
+}
+
+#[test]
+fn tables_work_in_the_compiled_program() {
+    let dir = scratch_dir("tables");
+    // 100 string keys take the hash part through several resizes; the last lines move keys
+    // from the hash part to the array part.
+    fs::write(dir.join("tables.lua"), "\
+local t = {}
+local i = 0
+while i < 100 do i = i + 1 t[\"k\" .. i] = i end
+local sum = 0
+i = 0
+while i < 100 do i = i + 1 sum = sum + t[\"k\" .. i] end
+print(sum, t.k1, t.k100)
+t = {1, 2}
+table.insert(t, 3)
+t[5] = 5
+t[4] = 4
+print(#t, t[4], t[5])
+").unwrap();
+    assert_eq!(run(&dir, &["-q", "tables.lua"]).status.code(), Some(0));
+
+    let Ok(built) = Command::new("cc").current_dir(&dir).args(["tables.c", "-o", "tables", "-lm"]).output() else {
+        eprintln!("no C compiler, skipping");
+        return;
+    };
+    assert!(built.status.success(), "{}", String::from_utf8_lossy(&built.stderr));
+
+    let output = Command::new(dir.join("tables")).output().unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "5050\t1\t100\n5\t4\t5\n");
+}
+
+#[test]
+fn dialect_comes_from_the_option_then_the_extension_then_defaults_to_basic() {
+    let dir = scratch_dir("dialect");
+    let basic = "LET x = 1\nPRINT x\n";
+    let lua = "local x = 1\nprint(x)\n";
+    fs::write(dir.join("a.bas"), basic).unwrap();
+    fs::write(dir.join("b.teeny"), basic).unwrap();
+    fs::write(dir.join("c.LUA"), lua).unwrap();
+    fs::write(dir.join("d.txt"), basic).unwrap();
+    fs::write(dir.join("e"), basic).unwrap();
+    fs::write(dir.join("f.txt"), lua).unwrap();
+    fs::write(dir.join("g.lua"), basic).unwrap();
+
+    for input in ["a.bas", "b.teeny", "c.LUA", "d.txt", "e"] {
+        assert_eq!(run(&dir, &["-q", input]).status.code(), Some(0), "{input}");
+    }
+
+    // Unknown extensions fall back to BASIC, and --dialect wins over the extension.
+    assert_eq!(run(&dir, &["-q", "f.txt"]).status.code(), Some(1));
+    assert_eq!(run(&dir, &["-q", "--dialect=lua", "f.txt"]).status.code(), Some(0));
+    assert_eq!(run(&dir, &["-q", "g.lua"]).status.code(), Some(1));
+    assert_eq!(run(&dir, &["-q", "--dialect=basic", "g.lua"]).status.code(), Some(0));
+
+    let output = run(&dir, &["--dialect=cobol", "a.bas"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown dialect: cobol"));
+}
+
+#[test]
+fn parser_trace_is_indented_by_depth() {
+    let dir = scratch_dir("trace");
+    // The error in the second statement unwinds several rules at once.
+    fs::write(dir.join("trace.lua"), "local a = f(1 + 2)\nlocal b = (a +\nlocal c = {a, b}\nprint(c)\n").unwrap();
+
+    let output = run(&dir, &["-q", "--emit=ast", "--trace-parser", "trace.lua"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let depths: Vec<(usize, &str)> = stderr.lines()
+        .filter_map(|line| line.strip_prefix("[parser] "))
+        .map(|line| {
+            let rule = line.trim_start_matches(' ');
+            ((line.len() - rule.len()) / 2, rule.split(' ').next().unwrap())
+        })
+        .collect();
+
+    // A rule is at most one level deeper than the one before it.
+    assert_eq!(depths[0], (0, "program"));
+    for pair in depths.windows(2) {
+        assert!(pair[1].0 <= pair[0].0 + 1, "{stderr}");
+    }
+
+    // Every statement starts back at the top, so each rule entered was exited.
+    let statements: Vec<usize> = depths.iter().filter(|(_, rule)| *rule == "stat").map(|(depth, _)| *depth).collect();
+    assert_eq!(statements, vec![0; 4], "{stderr}");
+}